@@ -3,18 +3,30 @@ use std::fs;
 use egui::{FontData, FontDefinitions, FontFamily, Visuals};
 
 use crate::{
-    state::AppState,
-    widgets::{mainpanel::render_main_panel, sidebar::render_sidebar},
+    notifications::DoNotDisturb,
+    state::{AppState, Command},
+    widgets::{
+        forward::render_forward_dialog, imex::render_imex_dialog, mainpanel::render_main_panel,
+        message_info::render_message_info, onboarding::render_onboarding,
+        search::render_search_bar, sidebar::render_sidebar,
+    },
 };
 
 pub struct App {
     state: AppState,
+    /// Unread count currently shown in the window title.
+    title_unread: usize,
+    /// State version the title was last checked for.
+    title_version: Option<u64>,
 }
 
 pub const FONT_LIGHT: &str = "OpenSans-Light";
 pub const FONT_REGULAR: &str = "OpenSans-Regular";
 pub const FONT_SEMI_BOLD: &str = "OpenSans-SemiBold";
 
+/// Storage key of the daily time without notifications.
+const DO_NOT_DISTURB_KEY: &str = "do_not_disturb";
+
 impl App {
     pub fn state(&self) -> &AppState {
         &self.state
@@ -24,6 +36,35 @@ impl App {
         &mut self.state
     }
 
+    /// Shows the unread messages of all accounts in the window title.
+    fn update_title(&mut self, ctx: &egui::Context) {
+        let version = self.state().state_version();
+        if self.title_version == Some(version) {
+            return;
+        }
+        self.title_version = Some(version);
+
+        let unread = self
+            .state()
+            .shared_state()
+            .shared_state
+            .accounts
+            .values()
+            .map(|account| account.fresh_msg_cnt)
+            .sum();
+        if unread == self.title_unread {
+            return;
+        }
+        self.title_unread = unread;
+
+        let title = if unread > 0 {
+            format!("Dreamer ({})", unread)
+        } else {
+            "Dreamer".to_string()
+        };
+        ctx.send_viewport_cmd(egui::ViewportCommand::Title(title));
+    }
+
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         cc.egui_ctx.set_visuals(Visuals::light());
 
@@ -56,15 +97,47 @@ impl App {
 
         cc.egui_ctx.set_fonts(fonts);
 
+        let state = AppState::new(&cc.egui_ctx);
+        let do_not_disturb = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, DO_NOT_DISTURB_KEY))
+            .flatten();
+        if do_not_disturb.is_some() {
+            state.send_command(Command::SetDoNotDisturb(do_not_disturb));
+        }
+
         App {
-            state: AppState::new(&cc.egui_ctx),
+            state,
+            title_unread: 0,
+            title_version: None,
         }
     }
 }
 
 impl eframe::App for App {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        render_sidebar(ctx, self.state());
-        render_main_panel(ctx, self.state_mut());
+        let has_accounts = !self.state().shared_state().shared_state.accounts.is_empty();
+        if !has_accounts || self.state().login_form.open {
+            render_onboarding(ctx, self.state_mut());
+        } else {
+            render_search_bar(ctx, self.state_mut());
+            render_sidebar(ctx, self.state_mut());
+            render_main_panel(ctx, self.state_mut());
+        }
+
+        self.update_title(ctx);
+        render_imex_dialog(ctx, self.state_mut());
+        render_forward_dialog(ctx, self.state_mut());
+        render_message_info(ctx, self.state_mut());
+    }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        let do_not_disturb: Option<DoNotDisturb> = self.state().shared_state().do_not_disturb;
+        eframe::set_value(storage, DO_NOT_DISTURB_KEY, &do_not_disturb);
+    }
+
+    /// Only the settings are stored, not the layout of the UI.
+    fn persist_egui_memory(&self) -> bool {
+        false
     }
 }
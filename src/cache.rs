@@ -0,0 +1,134 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+/// A map dropping the least recently used entries once their total cost exceeds a budget.
+#[derive(Debug)]
+pub struct Lru<K, V> {
+    entries: HashMap<K, Entry<V>>,
+    /// Keys by the time they were last used.
+    used: BTreeMap<u64, K>,
+    clock: u64,
+    cost: usize,
+    budget: usize,
+}
+
+#[derive(Debug)]
+struct Entry<V> {
+    value: V,
+    cost: usize,
+    used: u64,
+}
+
+impl<K: Clone + Eq + Hash, V> Lru<K, V> {
+    pub fn new(budget: usize) -> Self {
+        Lru {
+            entries: HashMap::new(),
+            used: BTreeMap::new(),
+            clock: 0,
+            cost: 0,
+            budget,
+        }
+    }
+
+    /// Total cost of the entries.
+    pub fn cost(&self) -> usize {
+        self.cost
+    }
+
+    /// Looks up an entry and marks it as used.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let entry = self.entries.get_mut(key)?;
+        self.used.remove(&entry.used);
+        self.clock += 1;
+        entry.used = self.clock;
+        self.used.insert(self.clock, key.clone());
+        Some(&entry.value)
+    }
+
+    /// Looks up an entry without marking it as used.
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.entries.get(key).map(|entry| &entry.value)
+    }
+
+    /// Adds an entry, dropping the least recently used ones while over the budget.
+    ///
+    /// The new entry is kept even if it exceeds the budget on its own.
+    pub fn insert(&mut self, key: K, value: V, cost: usize) {
+        self.remove(&key);
+        self.clock += 1;
+        self.used.insert(self.clock, key.clone());
+        self.entries.insert(
+            key,
+            Entry {
+                value,
+                cost,
+                used: self.clock,
+            },
+        );
+        self.cost += cost;
+
+        while self.cost > self.budget && self.entries.len() > 1 {
+            let oldest = match self.used.keys().next() {
+                Some(oldest) => *oldest,
+                None => break,
+            };
+            if let Some(key) = self.used.remove(&oldest) {
+                self.remove(&key);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let entry = self.entries.remove(key)?;
+        self.used.remove(&entry.used);
+        self.cost -= entry.cost;
+        Some(entry.value)
+    }
+
+    /// Removes the entries for which `keep` returns false.
+    pub fn retain(&mut self, mut keep: impl FnMut(&K, &V) -> bool) {
+        let used = &mut self.used;
+        let cost = &mut self.cost;
+        self.entries.retain(|key, entry| {
+            let kept = keep(key, &entry.value);
+            if !kept {
+                used.remove(&entry.used);
+                *cost -= entry.cost;
+            }
+            kept
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru() {
+        let mut lru = Lru::new(10);
+        lru.insert("a", 1, 4);
+        lru.insert("b", 2, 4);
+        assert_eq!(lru.get(&"a"), Some(&1));
+
+        // "b" was used least recently
+        lru.insert("c", 3, 4);
+        assert_eq!(lru.peek(&"b"), None);
+        assert_eq!(lru.peek(&"a"), Some(&1));
+        assert_eq!(lru.cost(), 8);
+
+        // replacing an entry replaces its cost
+        lru.insert("c", 4, 2);
+        assert_eq!(lru.cost(), 6);
+
+        // too large entries are kept on their own
+        lru.insert("d", 5, 20);
+        assert_eq!(lru.peek(&"d"), Some(&5));
+        assert_eq!(lru.cost(), 20);
+
+        lru.insert("e", 6, 1);
+        lru.retain(|_, value| *value != 6);
+        assert_eq!(lru.cost(), 0);
+        assert_eq!(lru.remove(&"e"), None);
+    }
+}
@@ -2,17 +2,25 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use super::types::{ChatItem, ChatMessage, ChatState, InnerChatMessage, Login, Viewtype};
+use super::types::{
+    ChatCategory, ChatFilter, ChatItem, ChatKind, ChatMessage, ChatState, ImexMode,
+    InnerChatMessage, Login, MessageInfo, MessageState, Reaction, SearchResult, ServerSettings,
+    Viewtype, MAX_LOADED, PAGE_SIZE,
+};
 use anyhow::{anyhow, bail, ensure, Context as _, Result};
 use chrono::prelude::*;
-use deltachat::chat::{ChatVisibility, MessageListOptions};
+use deltachat::chat::{ChatVisibility, MessageListOptions, MuteDuration};
+use deltachat::constants::{
+    Chattype, DC_GCL_ARCHIVED_ONLY, DC_GCL_FOR_FORWARDING, DC_GCL_NO_SPECIALS,
+};
 use deltachat::{
     chat::{self, Chat, ChatId},
     chatlist::Chatlist,
     contact::{Contact, ContactId},
     context::Context,
     message::{self, MsgId},
-    EventType,
+    reaction,
+    summary::SummaryPrefix,
 };
 use lazy_static::lazy_static;
 use log::*;
@@ -36,7 +44,7 @@ lazy_static! {
         .into();
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Account {
     pub state: Arc<RwLock<AccountState>>,
 }
@@ -46,6 +54,7 @@ pub struct AccountState {
     pub logged_in: Login,
     pub selected_chat_id: Option<ChatId>,
     pub selected_chat: Option<ChatState>,
+    pub show_archived: bool,
 }
 
 impl Account {
@@ -55,6 +64,7 @@ impl Account {
                 logged_in: Login::default(),
                 selected_chat_id: None,
                 selected_chat: None,
+                show_archived: false,
             })),
         };
 
@@ -68,39 +78,57 @@ impl Account {
     pub async fn import(&self, context: &Context, path: &Path) -> Result<()> {
         use deltachat::imex;
 
+        // progress is reported through `EventType::ImexProgress` while this runs
         imex::imex(context, imex::ImexMode::ImportBackup, path, None)
             .await
-            .map_err(|err| anyhow!("{}", err))?;
+            .map_err(|err| anyhow!("{:#}", err))?;
 
-        let events = context.get_event_emitter();
-        while let Some(event) = events.recv().await {
-            match event.typ {
-                EventType::ImexProgress(0) => {
-                    bail!("Failed to import");
-                }
-                EventType::ImexProgress(1000) => {
-                    context.start_io().await;
-                    break;
-                }
-                _ => {}
-            }
-        }
+        context.start_io().await;
 
         Ok(())
     }
 
-    pub async fn login(&self, context: &Context, email: &str, password: &str) -> Result<()> {
-        use deltachat::config::Config;
-        self.state.write().await.logged_in = Login::Progress(0);
+    /// Exports or imports backups and keys of an existing account.
+    ///
+    /// Exports write into the directory `path`, the written files are reported
+    /// through `EventType::ImexFileWritten`.
+    pub async fn imex(
+        &self,
+        context: &Context,
+        mode: ImexMode,
+        path: &Path,
+        passphrase: Option<String>,
+    ) -> Result<()> {
+        use deltachat::imex;
 
-        context
-            .set_config(Config::Addr, Some(email))
-            .await
-            .map_err(|err| anyhow!("{:?}", err))?;
-        context
-            .set_config(Config::MailPw, Some(password))
+        let mode = match mode {
+            ImexMode::ExportSelfKeys => imex::ImexMode::ExportSelfKeys,
+            ImexMode::ImportSelfKeys => imex::ImexMode::ImportSelfKeys,
+            ImexMode::ExportBackup => imex::ImexMode::ExportBackup,
+            ImexMode::ImportBackup => bail!("backups can only be imported into new accounts"),
+        };
+        imex::imex(context, mode, path, passphrase)
             .await
-            .map_err(|err| anyhow!("{:?}", err))?;
+            .map_err(|err| anyhow!("{:#}", err))?;
+
+        Ok(())
+    }
+
+    pub async fn login(
+        &self,
+        context: &Context,
+        email: &str,
+        password: &str,
+        settings: &ServerSettings,
+    ) -> Result<()> {
+        self.state.write().await.logged_in = Login::Progress(0);
+
+        for (key, value) in login_config(email, password, settings) {
+            context
+                .set_config(key, value.as_deref())
+                .await
+                .map_err(|err| anyhow!("failed to set {:?}: {:?}", key, err))?;
+        }
 
         self.configure(context).await?;
         Ok(())
@@ -118,27 +146,10 @@ impl Account {
             context
                 .configure()
                 .await
-                .map_err(|err| anyhow!("{:?}", err))?;
-
-            let events = context.get_event_emitter();
-            while let Some(event) = events.recv().await {
-                info!("configure event {:?}", event);
-                match event.typ {
-                    EventType::ConfigureProgress { progress, .. } => match progress {
-                        0 => {
-                            bail!("Failed to login");
-                        }
-                        1000 => {
-                            break;
-                        }
-                        _ => {}
-                    },
-                    EventType::ImapConnected(_) | EventType::SmtpConnected(_) => {
-                        break;
-                    }
-                    _ => {}
-                }
-            }
+                .map_err(|err| anyhow!("{:#}", err))?;
+
+            // `configure` only returns once it is done, so we can start io right away
+            context.start_io().await;
 
             Ok(())
         }
@@ -148,41 +159,63 @@ impl Account {
         &self,
         context: &Context,
         range: Option<(usize, usize)>,
+        filter: &ChatFilter,
     ) -> Result<((usize, usize), usize, Vec<ChatState>)> {
         if let Some((a, b)) = range {
             ensure!(a <= b, "invalid indicies");
         }
 
-        let chatlist = Chatlist::try_load(context, 0, None, None)
-            .await
-            .map_err(|err| anyhow!("failed to load chats: {:?}", err))?;
-        let total_len = chatlist.len();
-        let range = range.unwrap_or((0, total_len));
-        let stop_index = (range.1 + 1).min(total_len);
-        let start_index = range.0;
-        let len = stop_index.saturating_sub(start_index);
-
-        let mut chats = Vec::with_capacity(len);
-        for i in start_index..stop_index {
-            let chat_id = chatlist.get_chat_id(i).with_context(|| format!("{}", i))?;
-            let (_, chat_state) = load_chat_state(context.clone(), chat_id, &chatlist)
-                .await
-                .with_context(|| format!("chat id: {}", chat_id))?;
-            if let Some(s) = chat_state {
-                chats.push(s);
-            }
-        }
+        let flags = if self.state.read().await.show_archived {
+            DC_GCL_ARCHIVED_ONLY
+        } else {
+            DC_GCL_NO_SPECIALS
+        };
+        let query = Some(filter.query.trim()).filter(|query| !query.is_empty());
+        load_chats(context, flags, query, range, filter.category).await
+    }
 
-        Ok(((start_index, stop_index - 1), total_len, chats))
+    /// The chats messages can be forwarded to, "Saved messages" first.
+    pub async fn load_forward_targets(&self, context: &Context) -> Result<Vec<ChatState>> {
+        let (_, _, chats) = load_chats(
+            context,
+            DC_GCL_FOR_FORWARDING | DC_GCL_NO_SPECIALS,
+            None,
+            None,
+            ChatCategory::All,
+        )
+        .await?;
+        Ok(chats.into_iter().filter(|chat| chat.can_send).collect())
+    }
+
+    /// Sends failed messages again.
+    pub async fn resend_messages(&self, context: &Context, msg_ids: &[MsgId]) -> Result<()> {
+        chat::resend_msgs(context, msg_ids).await?;
+        Ok(())
+    }
+
+    /// Forwards messages to a chat of the same account.
+    pub async fn forward_messages(
+        &self,
+        context: &Context,
+        msg_ids: &[MsgId],
+        chat_id: ChatId,
+    ) -> Result<()> {
+        chat::forward_msgs(context, msg_ids, chat_id).await?;
+        Ok(())
+    }
+
+    pub async fn archived_chats_len(&self, context: &Context) -> Result<usize> {
+        let chatlist = Chatlist::try_load(context, DC_GCL_ARCHIVED_ONLY, None, None)
+            .await
+            .map_err(|err| anyhow!("failed to load archived chats: {:?}", err))?;
+        Ok(chatlist.len())
     }
 
     pub async fn select_chat(&self, context: &Context, chat_id: ChatId) -> Result<()> {
         info!("selecting chat {:?}", chat_id);
         let mut ls = self.state.write().await;
         ls.selected_chat_id = Some(chat_id);
-        let chatlist = Chatlist::try_load(context, 0, None, None)
-            .await
-            .map_err(|err| anyhow!("failed to load chats: {:?}", err))?;
+        let chatlist = load_chatlist_for(context, chat_id).await?;
         let (_, selected_chat) = load_chat_state(context.clone(), chat_id, &chatlist).await?;
         ls.selected_chat = selected_chat;
 
@@ -203,6 +236,19 @@ impl Account {
         Ok(())
     }
 
+    pub async fn mute_chat(&self, context: &Context, chat_id: ChatId, muted: bool) -> Result<()> {
+        info!("muting chat: {:?} {}", chat_id, muted);
+
+        let duration = if muted {
+            MuteDuration::Forever
+        } else {
+            MuteDuration::NotMuted
+        };
+        chat::set_muted(context, chat_id, duration).await?;
+
+        Ok(())
+    }
+
     pub async fn unpin_chat(&self, context: &Context, chat_id: ChatId) -> Result<()> {
         info!("unpinning chat: {:?}", chat_id);
 
@@ -264,11 +310,25 @@ impl Account {
         }
     }
 
-    pub async fn send_text_message(&self, context: &Context, text: String) -> Result<()> {
+    pub async fn send_text_message(
+        &self,
+        context: &Context,
+        text: String,
+        quote: Option<MsgId>,
+    ) -> Result<()> {
         if let Some(chat_id) = self.state.read().await.selected_chat_id {
-            chat::send_text_msg(context, chat_id, text)
-                .await
-                .map_err(|err| anyhow!("failed to send message: {}", err))?;
+            if quote.is_some() {
+                let mut msg = message::Message::new(deltachat::message::Viewtype::Text);
+                msg.set_text(text);
+                set_quote(context, &mut msg, quote).await?;
+                chat::send_msg(context, chat_id, &mut msg)
+                    .await
+                    .map_err(|err| anyhow!("failed to send message: {}", err))?;
+            } else {
+                chat::send_text_msg(context, chat_id, text)
+                    .await
+                    .map_err(|err| anyhow!("failed to send message: {}", err))?;
+            }
         } else {
             bail!("no chat selected, can not send message");
         }
@@ -283,13 +343,17 @@ impl Account {
         path: String,
         text: String,
         mime: Option<String>,
+        quote: Option<MsgId>,
     ) -> Result<()> {
         if let Some(chat_id) = self.state.read().await.selected_chat_id {
             let mut msg = message::Message::new(
-                deltachat::message::Viewtype::from_i32(typ.to_i32().unwrap()).unwrap(),
+                typ.to_i32()
+                    .and_then(deltachat::message::Viewtype::from_i32)
+                    .unwrap_or(deltachat::message::Viewtype::Unknown),
             );
             msg.set_text(text);
             msg.set_file(path, mime.as_deref());
+            set_quote(context, &mut msg, quote).await?;
 
             chat::send_msg(context, chat_id, &mut msg)
                 .await
@@ -303,13 +367,59 @@ impl Account {
 
     pub async fn accept_contact_request(&self, context: &Context, chat_id: ChatId) -> Result<()> {
         chat_id.accept(context).await?;
+        self.refresh_selected_chat(context).await?;
         Ok(())
     }
 
     pub async fn block_contact(&self, context: &Context, chat_id: ChatId) -> Result<()> {
         chat_id.block(context).await?;
+        self.deselect_chat(chat_id).await;
+        Ok(())
+    }
+
+    pub async fn delete_chat(&self, context: &Context, chat_id: ChatId) -> Result<()> {
+        info!("deleting chat: {:?}", chat_id);
+        chat_id.delete(context).await?;
+        self.deselect_chat(chat_id).await;
+        Ok(())
+    }
+
+    /// Stores `text` as the draft of `chat_id`, an empty text removes the draft.
+    pub async fn set_draft(&self, context: &Context, chat_id: ChatId, text: String) -> Result<()> {
+        if text.is_empty() {
+            chat_id.set_draft(context, None).await?;
+        } else {
+            let mut msg = message::Message::new(deltachat::message::Viewtype::Text);
+            msg.set_text(text);
+            chat_id.set_draft(context, Some(&mut msg)).await?;
+        }
+        Ok(())
+    }
+
+    /// The text of the draft of `chat_id`, empty if there is none.
+    pub async fn get_draft(&self, context: &Context, chat_id: ChatId) -> Result<String> {
+        let draft = chat_id.get_draft(context).await?;
+        Ok(draft.map(|msg| msg.get_text()).unwrap_or_default())
+    }
+
+    /// Reloads the state of the selected chat, e.g. to pick up a changed `can_send`.
+    pub async fn refresh_selected_chat(&self, context: &Context) -> Result<()> {
+        let mut ls = self.state.write().await;
+        if let Some(chat_id) = ls.selected_chat_id {
+            let chatlist = load_chatlist_for(context, chat_id).await?;
+            let (_, selected_chat) = load_chat_state(context.clone(), chat_id, &chatlist).await?;
+            ls.selected_chat = selected_chat;
+        }
         Ok(())
     }
+
+    async fn deselect_chat(&self, chat_id: ChatId) {
+        let mut ls = self.state.write().await;
+        if ls.selected_chat_id == Some(chat_id) {
+            ls.selected_chat_id = None;
+            ls.selected_chat = None;
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -324,6 +434,140 @@ fn get_timestamp(ts: i64) -> Option<DateTime<Utc>> {
     Some(DateTime::from_utc(naive, Utc))
 }
 
+/// Loads the chat list containing `chat_id`, which is the archive for archived chats.
+async fn load_chatlist_for(context: &Context, chat_id: ChatId) -> Result<Chatlist> {
+    let chatlist = Chatlist::try_load(context, DC_GCL_NO_SPECIALS, None, None)
+        .await
+        .map_err(|err| anyhow!("failed to load chats: {:?}", err))?;
+    if chatlist.get_index_for_id(chat_id).is_some() {
+        return Ok(chatlist);
+    }
+
+    Chatlist::try_load(context, DC_GCL_ARCHIVED_ONLY, None, None)
+        .await
+        .map_err(|err| anyhow!("failed to load archived chats: {:?}", err))
+}
+
+/// The config keys `configure` logs in with, `None` unsets a key to leave it to autoconfig.
+fn login_config(
+    email: &str,
+    password: &str,
+    settings: &ServerSettings,
+) -> Vec<(deltachat::config::Config, Option<String>)> {
+    use deltachat::config::Config;
+
+    let non_empty = |s: &str| Some(s.trim()).filter(|s| !s.is_empty()).map(str::to_string);
+    let imap_port = settings.imap_port.map(|p| p.to_string());
+    let smtp_port = settings.smtp_port.map(|p| p.to_string());
+    let imap_security = settings.imap_security.to_i32().unwrap().to_string();
+    let smtp_security = settings.smtp_security.to_i32().unwrap().to_string();
+    let certificate_checks = settings.certificate_checks.to_i32().unwrap().to_string();
+
+    vec![
+        (Config::Addr, Some(email.to_string())),
+        (Config::MailPw, Some(password.to_string())),
+        (Config::MailServer, non_empty(&settings.imap_host)),
+        (Config::MailPort, imap_port),
+        (Config::MailSecurity, Some(imap_security)),
+        (Config::MailUser, non_empty(&settings.imap_user)),
+        (Config::SendServer, non_empty(&settings.smtp_host)),
+        (Config::SendPort, smtp_port),
+        (Config::SendSecurity, Some(smtp_security)),
+        (Config::SendUser, non_empty(&settings.smtp_user)),
+        (Config::SendPw, non_empty(&settings.smtp_password)),
+        (
+            Config::ImapCertificateChecks,
+            Some(certificate_checks.clone()),
+        ),
+        (Config::SmtpCertificateChecks, Some(certificate_checks)),
+    ]
+}
+
+/// Loads the chats of the category, the range applies to the matching ones.
+async fn load_chats(
+    context: &Context,
+    flags: usize,
+    query: Option<&str>,
+    range: Option<(usize, usize)>,
+    category: ChatCategory,
+) -> Result<((usize, usize), usize, Vec<ChatState>)> {
+    let chatlist = Chatlist::try_load(context, flags, query, None)
+        .await
+        .map_err(|err| anyhow!("failed to load chats: {:?}", err))?;
+    // the category is checked on what is cheap to load, the state is only loaded for
+    // the chats in the range
+    let mut chat_ids = Vec::with_capacity(chatlist.len());
+    for i in 0..chatlist.len() {
+        let chat_id = chatlist.get_chat_id(i).with_context(|| format!("{}", i))?;
+        if category == ChatCategory::All
+            || category.matches(&load_chat_kind(context, chat_id).await?)
+        {
+            chat_ids.push(chat_id);
+        }
+    }
+    let total_len = chat_ids.len();
+    let range = range.unwrap_or((0, total_len));
+    let stop_index = (range.1 + 1).min(total_len);
+    let start_index = range.0.min(stop_index);
+    let len = stop_index - start_index;
+
+    let mut chats = Vec::with_capacity(len);
+    for &chat_id in &chat_ids[start_index..stop_index] {
+        let (_, chat_state) = load_chat_state(context.clone(), chat_id, &chatlist)
+            .await
+            .with_context(|| format!("chat id: {}", chat_id))?;
+        if let Some(s) = chat_state {
+            chats.push(s);
+        }
+    }
+
+    Ok((
+        (start_index, stop_index.saturating_sub(1)),
+        total_len,
+        chats,
+    ))
+}
+
+/// Sends copies of messages to a chat of another account.
+///
+/// `forward_msgs` only works within one account, so text and attachment are sent as new messages.
+pub async fn copy_messages(
+    from: &Context,
+    msg_ids: &[MsgId],
+    to: &Context,
+    chat_id: ChatId,
+) -> Result<()> {
+    for msg_id in msg_ids {
+        let original = message::Message::load_from_db(from, *msg_id)
+            .await
+            .map_err(|err| anyhow!("failed to load msg: {}: {}", msg_id, err))?;
+
+        let mut msg = message::Message::new(original.get_viewtype());
+        msg.set_text(original.get_text());
+        if let Some(file) = original.get_file(from) {
+            // the file is copied into the blobdir of the other account when sending
+            msg.set_file(file.to_string_lossy(), original.get_filemime().as_deref());
+        }
+        chat::send_msg(to, chat_id, &mut msg)
+            .await
+            .map_err(|err| anyhow!("failed to send copy of {}: {}", msg_id, err))?;
+    }
+    Ok(())
+}
+
+/// What the chat list is filtered by, without loading the whole state of the chat.
+async fn load_chat_kind(context: &Context, chat_id: ChatId) -> Result<ChatKind> {
+    let chat = Chat::load_from_db(context, chat_id)
+        .await
+        .with_context(|| format!("chat id: {}", chat_id))?;
+    Ok(ChatKind {
+        is_group: chat.get_type() == Chattype::Group,
+        is_single: chat.get_type() == Chattype::Single,
+        is_contact_request: chat.is_contact_request(),
+        fresh_msg_cnt: chat_id.get_fresh_msg_cnt(context).await?,
+    })
+}
+
 async fn load_chat_state(
     context: Context,
     chat_id: ChatId,
@@ -333,6 +577,7 @@ async fn load_chat_state(
         if let Some(index) = chats.get_index_for_id(chat_id) {
             let lot = chats.get_summary(&context, index, Some(&chat)).await?;
 
+            let is_draft = matches!(lot.prefix, Some(SummaryPrefix::Draft(_)));
             let header = lot
                 .prefix
                 .as_ref()
@@ -351,12 +596,15 @@ async fn load_chat_state(
                     name: chat.get_name().to_string(),
                     header,
                     preview,
+                    is_draft,
                     timestamp: get_timestamp(lot.timestamp),
                     state: lot.state.to_string(),
                     profile_image: chat.get_profile_image(&context).await?.map(Into::into),
                     can_send: chat.can_send(&context).await.unwrap_or_default(),
                     is_contact_request,
                     chat_type: chat.get_type().to_string(),
+                    is_group: chat.get_type() == Chattype::Group,
+                    is_single: chat.get_type() == Chattype::Single,
                     color: chat.get_color(&context).await?,
                     is_device_talk: chat.is_device_talk(),
                     is_self_talk: chat.is_self_talk(),
@@ -366,6 +614,7 @@ async fn load_chat_state(
                         .len(),
                     is_pinned: chat.get_visibility() == ChatVisibility::Pinned,
                     is_archived: chat.get_visibility() == ChatVisibility::Archived,
+                    is_muted: chat.is_muted(),
                 }),
             )
         } else {
@@ -403,8 +652,10 @@ async fn refresh_message_list(
 
     let total_len = chat_items.len();
 
-    // default to all
-    let range = range.unwrap_or((0, total_len));
+    // default to the newest page
+    let (start, end) = range.unwrap_or((total_len.saturating_sub(PAGE_SIZE), total_len));
+    let end = end.min(total_len);
+    let range = (start.min(end).max(end.saturating_sub(MAX_LOADED)), end);
 
     info!(
         "loading chat messages {:?} from ({}..={})",
@@ -417,8 +668,19 @@ async fn refresh_message_list(
     let mut chat_messages = Vec::with_capacity(len);
     let mut contacts = HashMap::new();
 
-    let mut last_contact_id = None;
-    let mut last_marker = true;
+    // whether the first message shows its sender depends on the item before the page
+    let (mut last_contact_id, mut last_marker) = match offset.checked_sub(1) {
+        Some(i) => match chat_items[i] {
+            ChatItem::Message(msg_id) => {
+                let msg = message::Message::load_from_db(&context, MsgId::new(msg_id))
+                    .await
+                    .map_err(|err| anyhow!("failed to load msg: {}: {}", msg_id, err))?;
+                (Some(msg.get_from_id()), false)
+            }
+            ChatItem::DayMarker(_) => (None, true),
+        },
+        None => (None, true),
+    };
     for chat_item in chat_items.iter().skip(offset).take(len) {
         match chat_item {
             ChatItem::Message(msg_id) => {
@@ -451,11 +713,19 @@ async fn refresh_message_list(
                 let mut inner_msg = InnerChatMessage {
                     id: msg.get_id().to_u32(),
                     from_id: msg.get_from_id().to_u32(),
-                    viewtype: Viewtype::from_i32(msg.get_viewtype().to_i32().unwrap()).unwrap(),
+                    viewtype: msg
+                        .get_viewtype()
+                        .to_i32()
+                        .and_then(Viewtype::from_i32)
+                        .unwrap_or(Viewtype::Unknown),
                     from_first_name: from.get_display_name().to_string(),
                     from_profile_image: from.get_profile_image(&context).await?.map(Into::into),
                     from_color: from.get_color(),
-                    state: msg.get_state().to_string(),
+                    state: msg
+                        .get_state()
+                        .to_i32()
+                        .and_then(MessageState::from_i32)
+                        .unwrap_or(MessageState::Undefined),
                     text: msg.get_text(),
                     quote: None,
                     timestamp: get_timestamp(msg.get_sort_timestamp()),
@@ -464,6 +734,10 @@ async fn refresh_message_list(
                     file_width: msg.get_width(),
                     file_height: msg.get_height(),
                     is_first,
+                    is_forwarded: msg.is_forwarded(),
+                    is_encrypted: msg.get_showpadlock(),
+                    is_edited: msg.is_edited(),
+                    reactions: load_reactions(&context, msg.get_id()).await?,
                 };
 
                 if let Some(quote) = msg.quoted_message(&context).await? {
@@ -482,6 +756,130 @@ async fn refresh_message_list(
     Ok((chat_id.to_u32(), range, chat_items, chat_messages))
 }
 
+/// Most messages loaded for one search.
+const SEARCH_LIMIT: usize = 100;
+
+/// Finds messages containing `query`, in one chat or in all chats.
+pub async fn search_messages(
+    context: &Context,
+    chat_id: Option<ChatId>,
+    query: &str,
+) -> Result<Vec<SearchResult>> {
+    let msg_ids = context.search_msgs(chat_id, query).await?;
+
+    let mut chat_names = HashMap::new();
+    let mut contact_names = HashMap::new();
+    let mut results = Vec::with_capacity(msg_ids.len().min(SEARCH_LIMIT));
+    for msg_id in msg_ids.into_iter().take(SEARCH_LIMIT) {
+        let msg = message::Message::load_from_db(context, msg_id)
+            .await
+            .map_err(|err| anyhow!("failed to load msg: {}: {}", msg_id, err))?;
+
+        let chat_name = match chat_names.get(&msg.get_chat_id()) {
+            Some(name) => String::clone(name),
+            None => {
+                let chat = Chat::load_from_db(context, msg.get_chat_id())
+                    .await
+                    .map_err(|err| anyhow!("failed to load chat: {:?}", err))?;
+                let name = chat.get_name().to_string();
+                chat_names.insert(msg.get_chat_id(), name.clone());
+                name
+            }
+        };
+        let from_name = match contact_names.get(&msg.get_from_id()) {
+            Some(name) => String::clone(name),
+            None => {
+                let contact = Contact::get_by_id(context, msg.get_from_id())
+                    .await
+                    .map_err(|err| {
+                        anyhow!("failed to load contact: {}: {}", msg.get_from_id(), err)
+                    })?;
+                let name = contact.get_display_name().to_string();
+                contact_names.insert(msg.get_from_id(), name.clone());
+                name
+            }
+        };
+
+        results.push(SearchResult {
+            account: context.get_id(),
+            chat_id: msg.get_chat_id().to_u32(),
+            chat_name,
+            msg_id: msg_id.to_u32(),
+            from_name,
+            timestamp: get_timestamp(msg.get_timestamp()),
+            text: msg.get_text(),
+        });
+    }
+
+    Ok(results)
+}
+
+pub async fn load_message_info(context: &Context, msg_id: MsgId) -> Result<MessageInfo> {
+    let msg = message::Message::load_from_db(context, msg_id)
+        .await
+        .map_err(|err| anyhow!("failed to load msg: {}: {}", msg_id, err))?;
+    let report = msg_id.get_info(context).await?;
+
+    // timestamps we don't know are 0
+    let timestamp = |ts: i64| if ts > 0 { get_timestamp(ts) } else { None };
+    let state = msg.get_state();
+    Ok(MessageInfo {
+        id: msg_id.to_u32(),
+        report,
+        sent: timestamp(msg.get_timestamp()),
+        received: timestamp(msg.get_received_timestamp()),
+        state: state.to_string(),
+        is_encrypted: msg.get_showpadlock(),
+        error: if state == message::MessageState::OutFailed {
+            msg.get_error()
+        } else {
+            None
+        },
+    })
+}
+
+/// Counts the reactions to `msg_id` by emoji, the most used first.
+pub async fn load_reactions(context: &Context, msg_id: MsgId) -> Result<Vec<Reaction>> {
+    let reactions = reaction::get_msg_reactions(context, msg_id).await?;
+
+    let mut counted: Vec<Reaction> = Vec::new();
+    for contact_id in reactions.contacts() {
+        let reaction = reactions.get(contact_id);
+        for emoji in reaction.emojis() {
+            let is_self = contact_id == ContactId::SELF;
+            match counted.iter_mut().find(|r| r.emoji == emoji) {
+                Some(r) => {
+                    r.count += 1;
+                    r.is_self |= is_self;
+                }
+                None => counted.push(Reaction {
+                    emoji: emoji.to_string(),
+                    count: 1,
+                    is_self,
+                }),
+            }
+        }
+    }
+    counted.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.emoji.cmp(&b.emoji)));
+
+    Ok(counted)
+}
+
+/// Makes `msg` a reply to the message `quote`.
+async fn set_quote(
+    context: &Context,
+    msg: &mut message::Message,
+    quote: Option<MsgId>,
+) -> Result<()> {
+    if let Some(quote) = quote {
+        let quote = message::Message::load_from_db(context, quote)
+            .await
+            .map_err(|err| anyhow!("failed to load quoted msg: {}: {}", quote, err))?;
+        msg.set_quote(context, Some(&quote)).await?;
+    }
+    Ok(())
+}
+
 async fn load_quote(
     context: &Context,
     contacts: &mut HashMap<ContactId, Contact>,
@@ -501,11 +899,19 @@ async fn load_quote(
     Ok(Box::new(InnerChatMessage {
         id: msg.get_id().to_u32(),
         from_id: msg.get_from_id().to_u32(),
-        viewtype: Viewtype::from_i32(msg.get_viewtype().to_i32().unwrap()).unwrap(),
+        viewtype: msg
+            .get_viewtype()
+            .to_i32()
+            .and_then(Viewtype::from_i32)
+            .unwrap_or(Viewtype::Unknown),
         from_first_name: from.get_display_name().to_string(),
         from_profile_image: from.get_profile_image(context).await?.map(Into::into),
         from_color: from.get_color(),
-        state: msg.get_state().to_string(),
+        state: msg
+            .get_state()
+            .to_i32()
+            .and_then(MessageState::from_i32)
+            .unwrap_or(MessageState::Undefined),
         text: msg.get_text(),
         quote: None,
         timestamp: get_timestamp(msg.get_sort_timestamp()),
@@ -514,5 +920,74 @@ async fn load_quote(
         file_width: msg.get_width(),
         file_height: msg.get_height(),
         is_first: true,
+        is_forwarded: msg.is_forwarded(),
+        is_encrypted: msg.get_showpadlock(),
+        is_edited: msg.is_edited(),
+        reactions: Vec::new(),
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dc::types::{CertificateChecks, Security};
+    use deltachat::config::Config;
+
+    #[test]
+    fn test_login_config() {
+        let value = |config: &[(Config, Option<String>)], key: Config| {
+            let (_, value) = config.iter().find(|(k, _)| *k == key).unwrap();
+            value.clone()
+        };
+
+        // without server settings, everything but the login is left to autoconfig
+        let config = login_config("alice@example.org", "secret", &ServerSettings::default());
+        assert_eq!(
+            value(&config, Config::Addr).as_deref(),
+            Some("alice@example.org")
+        );
+        assert_eq!(value(&config, Config::MailPw).as_deref(), Some("secret"));
+        assert_eq!(value(&config, Config::MailServer), None);
+        assert_eq!(value(&config, Config::MailPort), None);
+        assert_eq!(value(&config, Config::MailSecurity).as_deref(), Some("0"));
+        assert_eq!(value(&config, Config::SendUser), None);
+        assert_eq!(value(&config, Config::SendPw), None);
+        assert_eq!(
+            value(&config, Config::SmtpCertificateChecks).as_deref(),
+            Some("0")
+        );
+
+        let settings = ServerSettings {
+            imap_host: " imap.example.org ".into(),
+            imap_port: Some(993),
+            imap_security: Security::Ssl,
+            imap_user: "alice".into(),
+            smtp_host: "smtp.example.org".into(),
+            smtp_port: Some(587),
+            smtp_security: Security::Starttls,
+            smtp_user: "  ".into(),
+            smtp_password: "other".into(),
+            certificate_checks: CertificateChecks::AcceptInvalidCertificates,
+        };
+        let config = login_config("alice@example.org", "secret", &settings);
+        assert_eq!(
+            value(&config, Config::MailServer).as_deref(),
+            Some("imap.example.org")
+        );
+        assert_eq!(value(&config, Config::MailPort).as_deref(), Some("993"));
+        assert_eq!(value(&config, Config::MailSecurity).as_deref(), Some("1"));
+        assert_eq!(value(&config, Config::MailUser).as_deref(), Some("alice"));
+        assert_eq!(
+            value(&config, Config::SendServer).as_deref(),
+            Some("smtp.example.org")
+        );
+        assert_eq!(value(&config, Config::SendPort).as_deref(), Some("587"));
+        assert_eq!(value(&config, Config::SendSecurity).as_deref(), Some("2"));
+        assert_eq!(value(&config, Config::SendUser), None);
+        assert_eq!(value(&config, Config::SendPw).as_deref(), Some("other"));
+        assert_eq!(
+            value(&config, Config::ImapCertificateChecks).as_deref(),
+            Some("2")
+        );
+    }
+}
@@ -9,7 +9,6 @@ use deltachat::context::Context;
 use deltachat::{message, EventType};
 use futures::StreamExt;
 use log::{debug, error, info, warn};
-use num_traits::FromPrimitive;
 use tokio::runtime::Runtime;
 use tokio::sync::RwLock;
 
@@ -17,6 +16,9 @@ use super::{account, types::*};
 
 use super::account::*;
 
+/// Key of the ui config marking an account as muted.
+const UI_CONFIG_MUTED: &str = "ui.dreamer.muted";
+
 #[derive(Debug, Clone)]
 pub struct LocalState {
     rt: Arc<Runtime>,
@@ -29,6 +31,8 @@ struct LocalStateInner {
     account_states: HashMap<u32, Account>,
     accounts: deltachat::accounts::Accounts,
     errors: Vec<anyhow::Error>,
+    /// Applies to the chat list of every account.
+    chat_filter: ChatFilter,
 }
 
 impl LocalState {
@@ -59,10 +63,11 @@ impl LocalState {
         F: FnOnce(&mut account::AccountState),
     {
         let ls = self.inner.read().await;
-        let account = ls.account_states.get(&id).expect("missing account");
-
-        let state = &mut account.state.write().await;
-        f(state);
+        // events can still arrive for accounts that were just removed
+        if let Some(account) = ls.account_states.get(&id) {
+            let state = &mut account.state.write().await;
+            f(state);
+        }
     }
 
     pub fn subscribe_all(&self, rx: tokio::sync::mpsc::Sender<(u32, Event)>) {
@@ -87,46 +92,33 @@ impl LocalState {
         event: deltachat::Event,
     ) -> Result<()> {
         match event.typ {
-            EventType::ConfigureProgress { progress, .. } => {
-                if progress == 0 {
-                    self.with_account_state(event.id, |state| {
-                        state.logged_in = Login::Error("failed to login".into());
-                    })
-                    .await;
-                    rx.send((event.id, Event::Configure(Progress::Error)))
-                        .await?;
-                } else {
-                    let p = if progress == 1000 {
-                        Progress::Success
-                    } else {
+            EventType::ConfigureProgress { progress, comment } => {
+                let p = Progress::from_permille(progress);
+                match p {
+                    Progress::Error => {
+                        let err = comment.unwrap_or_else(|| "failed to login".into());
                         self.with_account_state(event.id, |state| {
-                            state.logged_in = Login::Progress(progress);
+                            state.logged_in = Login::Error(err);
                         })
                         .await;
-                        Progress::Step(progress)
-                    };
-                    rx.send((event.id, Event::Configure(p))).await?;
-                }
-            }
-            EventType::ImexProgress(progress) => {
-                if progress == 0 {
-                    self.with_account_state(event.id, |state| {
-                        state.logged_in = Login::Error("failed to import".into());
-                    })
-                    .await;
-                    rx.send((event.id, Event::Imex(Progress::Error))).await?;
-                } else {
-                    let p = if progress == 1000 {
-                        Progress::Success
-                    } else {
+                    }
+                    Progress::Step(step) => {
                         self.with_account_state(event.id, |state| {
-                            state.logged_in = Login::Progress(progress);
+                            state.logged_in = Login::Progress(step);
                         })
                         .await;
-                        Progress::Step(progress)
-                    };
-                    rx.send((event.id, Event::Imex(p))).await?;
+                    }
+                    // the state changes once the account is connected
+                    Progress::Success => {}
                 }
+                rx.send((event.id, Event::Configure(p))).await?;
+            }
+            EventType::ImexProgress(progress) => {
+                rx.send((event.id, Event::Imex(Progress::from_permille(progress))))
+                    .await?;
+            }
+            EventType::ImexFileWritten(path) => {
+                rx.send((event.id, Event::ImexFileWritten(path))).await?;
             }
             EventType::ImapConnected(_) | EventType::SmtpConnected(_) => {
                 info!("logged in");
@@ -156,6 +148,7 @@ impl LocalState {
                             chat_id: chat_id.to_u32(),
                             title: chat.get_name().to_string(),
                             body: msg.get_text(),
+                            is_muted: chat.is_muted(),
                         },
                     ))
                     .await?;
@@ -165,16 +158,48 @@ impl LocalState {
 
                 res?;
             }
-            EventType::MsgDelivered { chat_id, .. }
-            | EventType::MsgFailed { chat_id, .. }
-            | EventType::MsgsChanged { chat_id, .. }
-            | EventType::MsgRead { chat_id, .. }
-            | EventType::ChatModified(chat_id)
-            | EventType::MsgsNoticed(chat_id) => {
+            EventType::MsgDelivered { chat_id, msg_id }
+            | EventType::MsgFailed { chat_id, msg_id }
+            | EventType::MsgsChanged { chat_id, msg_id }
+            | EventType::MsgRead { chat_id, msg_id } => {
                 rx.send((
                     event.id,
                     Event::MessagesChanged {
                         chat_id: chat_id.to_u32(),
+                        // core uses 0 for several messages
+                        msg_id: Some(msg_id.to_u32()).filter(|id| *id != 0),
+                    },
+                ))
+                .await?;
+            }
+            EventType::ChatModified(chat_id) | EventType::MsgsNoticed(chat_id) => {
+                rx.send((
+                    event.id,
+                    Event::MessagesChanged {
+                        chat_id: chat_id.to_u32(),
+                        msg_id: None,
+                    },
+                ))
+                .await?;
+            }
+            EventType::ReactionsChanged {
+                chat_id, msg_id, ..
+            } => {
+                rx.send((
+                    event.id,
+                    Event::ReactionsChanged {
+                        chat_id: chat_id.to_u32(),
+                        msg_id: msg_id.to_u32(),
+                    },
+                ))
+                .await?;
+            }
+            EventType::MsgDeleted { chat_id, msg_id } => {
+                rx.send((
+                    event.id,
+                    Event::MessageDeleted {
+                        chat_id: chat_id.to_u32(),
+                        msg_id: msg_id.to_u32(),
                     },
                 ))
                 .await?;
@@ -209,21 +234,25 @@ impl LocalState {
         Ok((id, ctx.clone()))
     }
 
-    pub async fn login(&self, id: u32, ctx: &Context, email: &str, password: &str) -> Result<()> {
-        let res = self
-            .inner
-            .read()
-            .await
-            .account_states
-            .get(&id)
-            .unwrap()
-            .login(ctx, email, password)
-            .await;
+    /// Configures the freshly added account `id`.
+    ///
+    /// On failure the account is removed again and the error is returned.
+    pub async fn login(
+        &self,
+        id: u32,
+        ctx: &Context,
+        email: &str,
+        password: &str,
+        settings: &ServerSettings,
+    ) -> Result<()> {
+        let account = self.get_account(id).await?;
+        let res = account.login(ctx, email, password, settings).await;
         if let Err(err) = res {
             let mut ls = self.inner.write().await;
-            ls.errors.push(err);
+            ls.errors.push(anyhow!("{:#}", err));
             ls.account_states.remove(&id);
             ls.accounts.remove_account(id).await?;
+            return Err(err);
         }
 
         Ok(())
@@ -240,8 +269,15 @@ impl LocalState {
 
         let (resp2, resp3) = if let Some(account) = ls.account_states.get(&id) {
             // chat list
-            let (range, len, chats) = account.load_chat_list(&ctx, None).await?;
-            let resp2 = Some(ChatList { range, len, chats });
+            let (range, len, chats) = account.load_chat_list(&ctx, None, &ls.chat_filter).await?;
+            let resp2 = Some(ChatList {
+                range,
+                len,
+                chats,
+                archived: account.state.read().await.show_archived,
+                archived_len: account.archived_chats_len(&ctx).await?,
+                filter: ls.chat_filter.clone(),
+            });
 
             // send selected chat if exists
             let resp3 = if let Some(_selected_chat) = account.state.read().await.selected_chat_id {
@@ -266,26 +302,52 @@ impl LocalState {
         Ok((resp, resp2, resp3))
     }
 
+    /// Imports the backup at `path` into the freshly added account `id`.
+    ///
+    /// On failure the account is removed again and the error is returned.
     pub async fn import(&self, ctx: &Context, id: u32, path: &Path) -> Result<()> {
-        let res = self
-            .inner
-            .read()
-            .await
-            .account_states
-            .get(&id)
-            .unwrap()
-            .import(ctx, path)
-            .await;
+        let account = self.get_account(id).await?;
+        let res = account.import(ctx, path).await;
         if let Err(err) = res {
             let mut ls = self.inner.write().await;
-            ls.errors.push(err);
+            ls.errors.push(anyhow!("{:#}", err));
             ls.account_states.remove(&id);
             ls.accounts.remove_account(id).await?;
+            return Err(err);
         }
 
         Ok(())
     }
 
+    pub async fn imex(
+        &self,
+        account_id: u32,
+        mode: ImexMode,
+        path: &Path,
+        passphrase: Option<String>,
+    ) -> Result<()> {
+        let account = self.get_account(account_id).await?;
+        let ctx = self
+            .inner
+            .read()
+            .await
+            .accounts
+            .get_account(account_id)
+            .ok_or_else(|| anyhow!("invalid account: {}", account_id))?;
+        account.imex(&ctx, mode, path, passphrase).await
+    }
+
+    /// Clones the account out of the lock, so it isn't held while the account is busy.
+    async fn get_account(&self, id: u32) -> Result<Account> {
+        self.inner
+            .read()
+            .await
+            .account_states
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| anyhow!("invalid account: {}", id))
+    }
+
     pub async fn get_state(&self) -> SharedState {
         self.inner.read().await.to_response().await
     }
@@ -310,81 +372,78 @@ impl LocalState {
         }
     }
 
-    pub async fn pin_chat(&self, account_id: u32, chat_id: u32) -> Result<Response> {
+    pub async fn pin_chat(&self, account_id: u32, chat_id: u32) -> Result<()> {
         let ls = self.inner.write().await;
         if let Some(account) = ls.account_states.get(&account_id) {
             let ctx = ls.accounts.get_account(account_id).unwrap();
             let chat = ChatId::new(chat_id);
             account.pin_chat(&ctx, chat).await?;
 
-            let (chat_id, range, items, messages) = account.load_message_list(&ctx, None).await?;
+            Ok(())
+        } else {
+            Err(anyhow!("invalid account: {}-{}", account_id, chat_id))
+        }
+    }
 
-            Ok(Response::MessageList {
-                chat_id,
-                range,
-                items,
-                messages,
-            })
+    pub async fn mute_chat(&self, account_id: u32, chat_id: u32, muted: bool) -> Result<()> {
+        let ls = self.inner.write().await;
+        if let Some(account) = ls.account_states.get(&account_id) {
+            let ctx = ls.accounts.get_account(account_id).unwrap();
+            let chat = ChatId::new(chat_id);
+            account.mute_chat(&ctx, chat, muted).await?;
+
+            Ok(())
         } else {
             Err(anyhow!("invalid account: {}-{}", account_id, chat_id))
         }
     }
 
-    pub async fn unpin_chat(&self, account_id: u32, chat_id: u32) -> Result<Response> {
+    /// Turns the notifications of an account off or on, stored in its ui config.
+    pub async fn mute_account(&self, account_id: u32, muted: bool) -> Result<()> {
+        let ls = self.inner.read().await;
+        let ctx = ls
+            .accounts
+            .get_account(account_id)
+            .ok_or_else(|| anyhow!("invalid account: {}", account_id))?;
+        let value = if muted { Some("1") } else { None };
+        ctx.set_ui_config(UI_CONFIG_MUTED, value).await?;
+        Ok(())
+    }
+
+    pub async fn unpin_chat(&self, account_id: u32, chat_id: u32) -> Result<()> {
         let ls = self.inner.write().await;
         if let Some(account) = ls.account_states.get(&account_id) {
             let ctx = ls.accounts.get_account(account_id).unwrap();
             let chat = ChatId::new(chat_id);
             account.unpin_chat(&ctx, chat).await?;
 
-            let (chat_id, range, items, messages) = account.load_message_list(&ctx, None).await?;
-
-            Ok(Response::MessageList {
-                chat_id,
-                range,
-                items,
-                messages,
-            })
+            Ok(())
         } else {
             Err(anyhow!("invalid account: {}-{}", account_id, chat_id))
         }
     }
 
-    pub async fn archive_chat(&self, account_id: u32, chat_id: u32) -> Result<Response> {
+    pub async fn archive_chat(&self, account_id: u32, chat_id: u32) -> Result<()> {
         let ls = self.inner.write().await;
         if let Some(account) = ls.account_states.get(&account_id) {
             let ctx = ls.accounts.get_account(account_id).unwrap();
             let chat = ChatId::new(chat_id);
             account.archive_chat(&ctx, chat).await?;
 
-            let (chat_id, range, items, messages) = account.load_message_list(&ctx, None).await?;
-
-            Ok(Response::MessageList {
-                chat_id,
-                range,
-                items,
-                messages,
-            })
+            Ok(())
         } else {
             Err(anyhow!("invalid account: {}-{}", account_id, chat_id))
         }
     }
 
-    pub async fn unarchive_chat(&self, account_id: u32, chat_id: u32) -> Result<Response> {
+    pub async fn unarchive_chat(&self, account_id: u32, chat_id: u32) -> Result<()> {
         let ls = self.inner.write().await;
         if let Some(account) = ls.account_states.get(&account_id) {
             let ctx = ls.accounts.get_account(account_id).unwrap();
             let chat = ChatId::new(chat_id);
-            account.unpin_chat(&ctx, chat).await?;
-
-            let (chat_id, range, items, messages) = account.load_message_list(&ctx, None).await?;
+            account.unarchive_chat(&ctx, chat).await?;
 
-            Ok(Response::MessageList {
-                chat_id,
-                range,
-                items,
-                messages,
-            })
+            Ok(())
         } else {
             Err(anyhow!("invalid account: {}-{}", account_id, chat_id))
         }
@@ -416,12 +475,245 @@ impl LocalState {
         }
     }
 
+    pub async fn delete_chat(&self, account_id: u32, chat_id: u32) -> Result<()> {
+        let ls = self.inner.write().await;
+        if let Some(account) = ls.account_states.get(&account_id) {
+            let ctx = ls.accounts.get_account(account_id).unwrap();
+            let chat = ChatId::new(chat_id);
+            account.delete_chat(&ctx, chat).await?;
+
+            Ok(())
+        } else {
+            Err(anyhow!("invalid account: {}-{}", account_id, chat_id))
+        }
+    }
+
+    pub async fn set_draft(&self, account_id: u32, chat_id: u32, text: String) -> Result<()> {
+        let ls = self.inner.read().await;
+        if let Some(account) = ls.account_states.get(&account_id) {
+            let ctx = ls.accounts.get_account(account_id).unwrap();
+            account.set_draft(&ctx, ChatId::new(chat_id), text).await
+        } else {
+            Err(anyhow!("invalid account: {}-{}", account_id, chat_id))
+        }
+    }
+
+    pub async fn get_draft(&self, account_id: u32, chat_id: u32) -> Result<String> {
+        let ls = self.inner.read().await;
+        if let Some(account) = ls.account_states.get(&account_id) {
+            let ctx = ls.accounts.get_account(account_id).unwrap();
+            account.get_draft(&ctx, ChatId::new(chat_id)).await
+        } else {
+            Err(anyhow!("invalid account: {}-{}", account_id, chat_id))
+        }
+    }
+
+    /// The chats of an account messages can be forwarded to.
+    pub async fn load_forward_targets(&self, account_id: u32) -> Result<Vec<ChatState>> {
+        let ls = self.inner.read().await;
+        if let Some(account) = ls.account_states.get(&account_id) {
+            let ctx = ls.accounts.get_account(account_id).unwrap();
+            account.load_forward_targets(&ctx).await
+        } else {
+            Err(anyhow!("invalid account: {}", account_id))
+        }
+    }
+
+    pub async fn resend_messages(&self, account_id: u32, msg_ids: Vec<u32>) -> Result<()> {
+        let ls = self.inner.read().await;
+        let account = ls
+            .account_states
+            .get(&account_id)
+            .ok_or_else(|| anyhow!("invalid account: {}", account_id))?;
+        let ctx = ls.accounts.get_account(account_id).unwrap();
+        let msg_ids: Vec<_> = msg_ids.into_iter().map(message::MsgId::new).collect();
+        account.resend_messages(&ctx, &msg_ids).await
+    }
+
+    /// Forwards messages to a chat, copying them if the chat belongs to another account.
+    pub async fn forward_messages(
+        &self,
+        from_account: u32,
+        msg_ids: Vec<u32>,
+        to_account: u32,
+        chat_id: u32,
+    ) -> Result<()> {
+        let ls = self.inner.read().await;
+        let from = ls
+            .accounts
+            .get_account(from_account)
+            .ok_or_else(|| anyhow!("invalid account: {}", from_account))?;
+        let msg_ids: Vec<_> = msg_ids.into_iter().map(message::MsgId::new).collect();
+        let chat_id = ChatId::new(chat_id);
+
+        if from_account == to_account {
+            let account = ls
+                .account_states
+                .get(&from_account)
+                .ok_or_else(|| anyhow!("invalid account: {}", from_account))?;
+            account.forward_messages(&from, &msg_ids, chat_id).await
+        } else {
+            let to = ls
+                .accounts
+                .get_account(to_account)
+                .ok_or_else(|| anyhow!("invalid account: {}", to_account))?;
+            account::copy_messages(&from, &msg_ids, &to, chat_id).await
+        }
+    }
+
+    /// Deletes messages from this device and the server, and with `for_all` also
+    /// from the devices of the other chat members.
+    pub async fn delete_messages(
+        &self,
+        account_id: u32,
+        msg_ids: Vec<u32>,
+        for_all: bool,
+    ) -> Result<()> {
+        let ls = self.inner.read().await;
+        let ctx = ls
+            .accounts
+            .get_account(account_id)
+            .ok_or_else(|| anyhow!("invalid account: {}", account_id))?;
+        let msg_ids: Vec<_> = msg_ids.into_iter().map(message::MsgId::new).collect();
+        message::delete_msgs_ex(&ctx, &msg_ids, for_all).await?;
+        Ok(())
+    }
+
+    /// Sets our reaction to a message, an empty reaction removes it.
+    pub async fn send_reaction(
+        &self,
+        account_id: u32,
+        msg_id: u32,
+        reaction: String,
+    ) -> Result<()> {
+        let ls = self.inner.read().await;
+        let ctx = ls
+            .accounts
+            .get_account(account_id)
+            .ok_or_else(|| anyhow!("invalid account: {}", account_id))?;
+        deltachat::reaction::send_reaction(&ctx, message::MsgId::new(msg_id), &reaction).await?;
+        Ok(())
+    }
+
+    /// Replaces the text of an own message, for us and the other chat members.
+    pub async fn edit_message(&self, account_id: u32, msg_id: u32, text: String) -> Result<()> {
+        let ls = self.inner.read().await;
+        let ctx = ls
+            .accounts
+            .get_account(account_id)
+            .ok_or_else(|| anyhow!("invalid account: {}", account_id))?;
+        deltachat::chat::send_edit_request(&ctx, message::MsgId::new(msg_id), text).await?;
+        Ok(())
+    }
+
+    pub async fn load_reactions(&self, account_id: u32, msg_id: u32) -> Result<Vec<Reaction>> {
+        let ls = self.inner.read().await;
+        let ctx = ls
+            .accounts
+            .get_account(account_id)
+            .ok_or_else(|| anyhow!("invalid account: {}", account_id))?;
+        account::load_reactions(&ctx, message::MsgId::new(msg_id)).await
+    }
+
+    pub async fn load_message_info(&self, account_id: u32, msg_id: u32) -> Result<MessageInfo> {
+        let ls = self.inner.read().await;
+        let ctx = ls
+            .accounts
+            .get_account(account_id)
+            .ok_or_else(|| anyhow!("invalid account: {}", account_id))?;
+        account::load_message_info(&ctx, message::MsgId::new(msg_id)).await
+    }
+
+    /// Searches the messages of one account, or of all accounts if `account_id` is `None`.
+    ///
+    /// The newest messages come first.
+    pub async fn search_messages(
+        &self,
+        account_id: Option<u32>,
+        query: &str,
+    ) -> Result<Vec<SearchResult>> {
+        let ls = self.inner.read().await;
+        let account_ids: Vec<u32> = match account_id {
+            Some(id) => vec![id],
+            None => ls.account_states.keys().copied().collect(),
+        };
+
+        let mut results = Vec::new();
+        for id in account_ids {
+            let found = match ls.accounts.get_account(id) {
+                Some(ctx) => account::search_messages(&ctx, None, query).await,
+                None => Err(anyhow!("invalid account: {}", id)),
+            };
+            match found {
+                Ok(found) => results.extend(found),
+                // one broken account doesn't hide the results of the others
+                Err(err) if account_id.is_none() => {
+                    warn!("failed to search account {}: {:#}", id, err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        results.sort_by_key(|result| std::cmp::Reverse(result.timestamp));
+
+        Ok(results)
+    }
+
+    /// Finds the messages of a chat containing `query`, including the ones not loaded.
+    pub async fn search_chat(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        query: &str,
+    ) -> Result<Vec<u32>> {
+        let ls = self.inner.read().await;
+        let ctx = ls
+            .accounts
+            .get_account(account_id)
+            .ok_or_else(|| anyhow!("invalid account: {}", account_id))?;
+        let msg_ids = ctx.search_msgs(Some(ChatId::new(chat_id)), query).await?;
+        Ok(msg_ids.into_iter().map(|id| id.to_u32()).collect())
+    }
+
+    /// Counts the unread messages of an account.
+    pub async fn fresh_msg_count(&self, account_id: u32) -> Result<usize> {
+        let ls = self.inner.read().await;
+        let ctx = ls
+            .accounts
+            .get_account(account_id)
+            .ok_or_else(|| anyhow!("invalid account: {}", account_id))?;
+        Ok(ctx.get_fresh_msgs().await?.len())
+    }
+
+    /// Switches the chat list of the selected account between normal and archived chats.
+    pub async fn show_archived(&self, show: bool) -> Result<()> {
+        let ls = self.inner.read().await;
+        if let Some((account, _ctx)) = ls.get_selected_account().await {
+            account.state.write().await.show_archived = show;
+            Ok(())
+        } else {
+            Err(anyhow!("no selected account"))
+        }
+    }
+
+    pub async fn filter_chats(&self, filter: ChatFilter) {
+        self.inner.write().await.chat_filter = filter;
+    }
+
     pub async fn load_chat_list(&self, range: Option<(usize, usize)>) -> Result<ChatList> {
         let ls = self.inner.read().await;
         if let Some((account, ctx)) = ls.get_selected_account().await {
             info!("Loading chat list");
-            match account.load_chat_list(&ctx, range).await {
-                Ok((range, len, chats)) => Ok(ChatList { range, len, chats }),
+            let archived = account.state.read().await.show_archived;
+            let archived_len = account.archived_chats_len(&ctx).await.unwrap_or_default();
+            match account.load_chat_list(&ctx, range, &ls.chat_filter).await {
+                Ok((range, len, chats)) => Ok(ChatList {
+                    range,
+                    len,
+                    chats,
+                    archived,
+                    archived_len,
+                    filter: ls.chat_filter.clone(),
+                }),
                 Err(err) => {
                     warn!("Could not load chat list: {}", err);
                     // send an empty chat list to be handled by frontend
@@ -429,6 +721,9 @@ impl LocalState {
                         range: (0, 0),
                         len: 0,
                         chats: Vec::new(),
+                        archived,
+                        archived_len,
+                        filter: ls.chat_filter.clone(),
                     })
                 }
             }
@@ -469,10 +764,12 @@ impl LocalState {
         }
     }
 
-    pub async fn send_text_message(&self, text: String) -> Result<()> {
+    pub async fn send_text_message(&self, text: String, quote: Option<u32>) -> Result<()> {
         let ls = self.inner.read().await;
         if let Some((account, ctx)) = ls.get_selected_account().await {
-            account.send_text_message(&ctx, text).await?;
+            account
+                .send_text_message(&ctx, text, quote.map(message::MsgId::new))
+                .await?;
             Ok(())
         } else {
             Err(anyhow!("no account selected"))
@@ -485,17 +782,12 @@ impl LocalState {
         path: String,
         text: String,
         mime: Option<String>,
+        quote: Option<u32>,
     ) -> Result<()> {
         let ls = self.inner.read().await;
         if let Some((account, ctx)) = ls.get_selected_account().await {
             account
-                .send_file_message(
-                    &ctx,
-                    Viewtype::from_i32(typ as i32).unwrap(),
-                    path,
-                    text,
-                    mime,
-                )
+                .send_file_message(&ctx, typ, path, text, mime, quote.map(message::MsgId::new))
                 .await?;
             Ok(())
         } else {
@@ -520,10 +812,10 @@ impl LocalStateInner {
         let account_ids = accounts.get_all();
 
         if account_ids.is_empty() {
-            panic!(
-                "There are no available acccounts in your accounts.toml file: {}",
-                HOME_DIR.to_str().unwrap()
-            )
+            info!(
+                "no accounts configured in {}, waiting for onboarding",
+                HOME_DIR.display()
+            );
         }
 
         for id in account_ids.iter() {
@@ -541,6 +833,7 @@ impl LocalStateInner {
             accounts,
             account_states,
             errors: Vec::new(),
+            chat_filter: ChatFilter::default(),
         })
     }
 
@@ -576,7 +869,12 @@ impl LocalStateInner {
             let account = &account.state.read().await;
             let ctx = self.accounts.get_account(*id).unwrap();
             use deltachat::config::Config;
-            let email = ctx.get_config(Config::Addr).await.unwrap().unwrap();
+            // accounts that are still being set up might not have an address yet
+            let email = ctx
+                .get_config(Config::Addr)
+                .await
+                .unwrap()
+                .unwrap_or_default();
             let profile_image = ctx
                 .get_config(Config::Selfavatar)
                 .await
@@ -591,6 +889,13 @@ impl LocalStateInner {
                     email,
                     profile_image,
                     display_name,
+                    blobdir: ctx.get_blobdir().to_path_buf(),
+                    fresh_msg_cnt: ctx.get_fresh_msgs().await.unwrap_or_default().len(),
+                    is_muted: ctx
+                        .get_ui_config(UI_CONFIG_MUTED)
+                        .await
+                        .unwrap_or_default()
+                        .is_some(),
                 },
             );
         }
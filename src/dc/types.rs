@@ -6,6 +6,9 @@ use num_derive::{FromPrimitive, ToPrimitive};
 
 pub enum Message {}
 
+/// Mirrors `deltachat::contact::ContactId::SELF`.
+pub const CONTACT_ID_SELF: u32 = 1;
+
 #[derive(Debug)]
 pub enum Response {
     RemoteUpdate {
@@ -33,6 +36,13 @@ pub enum Response {
     },
 }
 
+/// Number of chat items loaded at once.
+pub const PAGE_SIZE: usize = 50;
+
+/// Most chat items kept loaded, the ones furthest from the last loaded page are dropped.
+pub const MAX_LOADED: usize = 4 * PAGE_SIZE;
+
+/// All items of a chat, with the messages of the items in `range` loaded.
 #[derive(Debug, Default, PartialEq, Clone)]
 pub struct MessageList {
     pub chat_id: u32,
@@ -42,12 +52,110 @@ pub struct MessageList {
 }
 
 impl MessageList {
+    /// The loaded message of the item at `index`.
+    pub fn get(&self, index: usize) -> Option<&ChatMessage> {
+        index
+            .checked_sub(self.range.0)
+            .and_then(|i| self.messages.get(i))
+    }
+
+    /// The range to load when the chat changed, following new messages if the end is loaded.
+    pub fn reload_range(&self) -> Option<(usize, usize)> {
+        if self.items.is_empty() {
+            None
+        } else if self.range.1 >= self.items.len() {
+            Some((self.range.0, usize::MAX))
+        } else {
+            Some(self.range)
+        }
+    }
+
+    /// Adds a page of the same chat, dropping the loaded messages furthest from it.
+    ///
+    /// A page that isn't next to the loaded messages, or that was loaded after the
+    /// chat changed, replaces them.
+    pub fn merge(&mut self, page: MessageList) {
+        let (start, end) = self.range;
+        if page.chat_id != self.chat_id
+            || page.items != self.items
+            || page.range.0 > end
+            || page.range.1 < start
+        {
+            *self = page;
+            return;
+        }
+
+        let mut messages = std::mem::take(&mut self.messages);
+        let after = messages.split_off(page.range.1.saturating_sub(start).min(messages.len()));
+        messages.truncate(page.range.0.saturating_sub(start));
+        messages.extend(page.messages);
+        messages.extend(after);
+        self.messages = messages;
+        self.range = (start.min(page.range.0), end.max(page.range.1));
+
+        let excess = self.messages.len().saturating_sub(MAX_LOADED);
+        if excess > 0 {
+            if page.range.0 <= start {
+                self.messages.truncate(MAX_LOADED);
+                self.range.1 -= excess;
+            } else {
+                self.messages.drain(..excess);
+                self.range.0 += excess;
+            }
+        }
+    }
+
     pub fn clear(&mut self) {
         self.chat_id = 0;
         self.range = (0, 0);
         self.items.clear();
         self.messages.clear();
     }
+
+    /// Removes a deleted message, together with a day marker left without messages.
+    ///
+    /// Returns the ids of the messages whose rendering changed, as the next message might
+    /// have to show the sender now.
+    pub fn remove_message(&mut self, id: u32) -> Option<Vec<u32>> {
+        let index = self.messages.iter().position(|msg| msg.id() == Some(id))?;
+        self.messages.remove(index);
+        if let Some(pos) = self
+            .items
+            .iter()
+            .position(|item| item == &ChatItem::Message(id))
+        {
+            self.items.remove(pos);
+        }
+
+        let mut changed = vec![id];
+        let (previous_from, previous_marker) = match index.checked_sub(1).map(|i| &self.messages[i])
+        {
+            Some(ChatMessage::Message(msg)) => (Some(msg.from_id), None),
+            Some(ChatMessage::DayMarker(time)) => (None, Some(*time)),
+            None => (None, None),
+        };
+        match self.messages.get_mut(index) {
+            Some(ChatMessage::Message(next)) => {
+                if !next.is_first && previous_from != Some(next.from_id) {
+                    next.is_first = true;
+                    changed.push(next.id);
+                }
+            }
+            Some(ChatMessage::DayMarker(_)) | None => {
+                if let Some(time) = previous_marker {
+                    let marker = ChatItem::DayMarker(time);
+                    self.messages.remove(index - 1);
+                    if let Some(pos) = self.items.iter().position(|item| item == &marker) {
+                        self.items.remove(pos);
+                    }
+                    self.range.1 = self.range.1.saturating_sub(1);
+                }
+            }
+        }
+        self.range.1 = self.range.1.saturating_sub(1);
+
+        Some(changed)
+    }
 }
 
 #[derive(Debug, Default, PartialEq, Clone)]
@@ -55,20 +163,39 @@ pub struct ChatList {
     pub range: (usize, usize),
     pub len: usize,
     pub chats: Vec<ChatState>,
+    /// Whether this lists the archived chats.
+    pub archived: bool,
+    /// Number of archived chats.
+    pub archived_len: usize,
+    /// The filter the chats were loaded with.
+    pub filter: ChatFilter,
 }
 
 #[derive(Debug, PartialEq)]
 pub enum Event {
     Configure(Progress),
     Imex(Progress),
+    ImexFileWritten(PathBuf),
     Connected,
     MessagesChanged {
         chat_id: u32,
+        /// The changed message, `None` if several messages or the chat changed.
+        msg_id: Option<u32>,
     },
     MessageIncoming {
         chat_id: u32,
         title: String,
         body: String,
+        /// The chat is muted.
+        is_muted: bool,
+    },
+    ReactionsChanged {
+        chat_id: u32,
+        msg_id: u32,
+    },
+    MessageDeleted {
+        chat_id: u32,
+        msg_id: u32,
     },
     Log(Log),
 }
@@ -80,6 +207,17 @@ pub enum Progress {
     Step(usize),
 }
 
+impl Progress {
+    /// Maps the progress of configure and imex events, in permille with 0 for errors.
+    pub fn from_permille(progress: usize) -> Self {
+        match progress {
+            0 => Progress::Error,
+            1000 => Progress::Success,
+            _ => Progress::Step(progress),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum Log {
     Info(String),
@@ -101,7 +239,7 @@ pub struct SharedState {
     pub selected_chat: Option<ChatState>,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum ChatItem {
     Message(u32),
     DayMarker(DateTime<Utc>),
@@ -130,7 +268,7 @@ pub struct InnerChatMessage {
     pub from_profile_image: Option<PathBuf>,
     pub from_color: u32,
     pub viewtype: Viewtype,
-    pub state: String,
+    pub state: MessageState,
     pub text: String,
     pub quote: Option<Box<InnerChatMessage>>,
     pub timestamp: Option<DateTime<Utc>>,
@@ -139,6 +277,54 @@ pub struct InnerChatMessage {
     pub file_height: i32,
     pub file_width: i32,
     pub is_first: bool,
+    pub is_forwarded: bool,
+    /// Whether the message was end-to-end encrypted.
+    pub is_encrypted: bool,
+    /// Whether the text was changed after sending.
+    pub is_edited: bool,
+    pub reactions: Vec<Reaction>,
+}
+
+impl InnerChatMessage {
+    /// Whether deltachat allows deleting the message for everyone or editing it.
+    pub fn is_own(&self) -> bool {
+        self.from_id == CONTACT_ID_SELF && !self.is_info && self.is_encrypted
+    }
+}
+
+/// How often an emoji was used to react to a message.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Reaction {
+    pub emoji: String,
+    pub count: usize,
+    /// Whether we reacted with this emoji ourselves.
+    pub is_self: bool,
+}
+
+/// A message found by a search.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SearchResult {
+    pub account: u32,
+    pub chat_id: u32,
+    pub chat_name: String,
+    pub msg_id: u32,
+    pub from_name: String,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub text: String,
+}
+
+/// Details about a single message, as shown in its info dialog.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MessageInfo {
+    pub id: u32,
+    /// The report of deltachat, with headers and delivery details.
+    pub report: String,
+    pub sent: Option<DateTime<Utc>>,
+    pub received: Option<DateTime<Utc>>,
+    pub state: String,
+    pub is_encrypted: bool,
+    /// Why sending failed, for messages that did.
+    pub error: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -148,6 +334,8 @@ pub struct ChatState {
     pub name: String,
     pub header: String,
     pub preview: String,
+    /// The preview shows the draft of this chat.
+    pub is_draft: bool,
     pub timestamp: Option<DateTime<Utc>>,
     pub state: String,
     pub profile_image: Option<PathBuf>,
@@ -157,10 +345,53 @@ pub struct ChatState {
     pub is_self_talk: bool,
     pub is_device_talk: bool,
     pub chat_type: String,
+    pub is_group: bool,
+    /// A chat with one contact.
+    pub is_single: bool,
     pub color: u32,
     pub member_count: usize,
     pub is_pinned: bool,
     pub is_archived: bool,
+    pub is_muted: bool,
+}
+
+/// Restricts the chat list to some of the chats.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ChatFilter {
+    /// Matched against the chat names by deltachat.
+    pub query: String,
+    pub category: ChatCategory,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ChatCategory {
+    #[default]
+    All,
+    Unread,
+    Groups,
+    Single,
+    Requests,
+}
+
+/// What the chat list is filtered by, cheaper to load than a [`ChatState`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChatKind {
+    pub is_group: bool,
+    pub is_single: bool,
+    pub is_contact_request: bool,
+    pub fresh_msg_cnt: usize,
+}
+
+impl ChatCategory {
+    pub fn matches(self, chat: &ChatKind) -> bool {
+        match self {
+            ChatCategory::All => true,
+            ChatCategory::Unread => chat.fresh_msg_cnt > 0,
+            ChatCategory::Groups => chat.is_group,
+            ChatCategory::Single => chat.is_single,
+            ChatCategory::Requests => chat.is_contact_request,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -177,6 +408,44 @@ impl Default for Login {
     }
 }
 
+/// Manual server configuration for accounts where autoconfig fails.
+///
+/// Empty fields are left to autoconfig.
+#[derive(Debug, Default, PartialEq, Clone, Eq)]
+pub struct ServerSettings {
+    pub imap_host: String,
+    pub imap_port: Option<u16>,
+    pub imap_security: Security,
+    pub imap_user: String,
+    pub smtp_host: String,
+    pub smtp_port: Option<u16>,
+    pub smtp_security: Security,
+    pub smtp_user: String,
+    pub smtp_password: String,
+    pub certificate_checks: CertificateChecks,
+}
+
+/// Mirrors `deltachat::provider::Socket`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[repr(i32)]
+pub enum Security {
+    #[default]
+    Automatic = 0,
+    Ssl = 1,
+    Starttls = 2,
+    Plain = 3,
+}
+
+/// Mirrors `deltachat::login_param::CertificateChecks`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[repr(i32)]
+pub enum CertificateChecks {
+    #[default]
+    Automatic = 0,
+    Strict = 1,
+    AcceptInvalidCertificates = 2,
+}
+
 #[derive(Debug, PartialEq, Clone, Eq)]
 pub struct AccountInfo {
     pub account: u32,
@@ -190,6 +459,12 @@ pub struct SharedAccountState {
     pub email: String,
     pub profile_image: Option<PathBuf>,
     pub display_name: Option<String>,
+    /// Directory where the account keeps its files.
+    pub blobdir: PathBuf,
+    /// Number of unread messages in chats that are not muted.
+    pub fresh_msg_cnt: usize,
+    /// No notifications are shown for this account.
+    pub is_muted: bool,
 }
 
 #[derive(Debug)]
@@ -266,4 +541,198 @@ pub enum Viewtype {
     File = 60,
     VideochatInvitation = 70,
     Webxdc = 80,
+    Vcard = 90,
+}
+
+impl Viewtype {
+    /// The view type a file with the given MIME type is sent as.
+    pub fn from_mime(mime: &str) -> Self {
+        match mime.split_once('/') {
+            Some(("image", "gif")) => Viewtype::Gif,
+            Some(("image", _)) => Viewtype::Image,
+            Some(("video", _)) => Viewtype::Video,
+            Some(("audio", _)) => Viewtype::Audio,
+            _ => Viewtype::File,
+        }
+    }
+}
+
+/// Mirrors `deltachat::message::MessageState`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[repr(i32)]
+pub enum MessageState {
+    Undefined = 0,
+    InFresh = 10,
+    InNoticed = 13,
+    InSeen = 16,
+    OutPreparing = 18,
+    OutDraft = 19,
+    OutPending = 20,
+    OutFailed = 24,
+    OutDelivered = 26,
+    OutMdnRcvd = 28,
+}
+
+/// Mirrors `deltachat::imex::ImexMode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[repr(i32)]
+pub enum ImexMode {
+    ExportSelfKeys = 1,
+    ImportSelfKeys = 2,
+    ExportBackup = 11,
+    ImportBackup = 12,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: u32, from_id: u32, is_first: bool) -> ChatMessage {
+        ChatMessage::Message(InnerChatMessage {
+            id,
+            from_id,
+            from_first_name: String::new(),
+            from_profile_image: None,
+            from_color: 0,
+            viewtype: Viewtype::Text,
+            state: MessageState::Undefined,
+            text: String::new(),
+            quote: None,
+            timestamp: None,
+            is_info: false,
+            file: None,
+            file_height: 0,
+            file_width: 0,
+            is_first,
+            is_forwarded: false,
+            is_encrypted: false,
+            is_edited: false,
+            reactions: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn test_message_list_remove_message() {
+        let day = |d: i64| DateTime::from_timestamp(d * 86_400, 0).unwrap();
+        let mut list = MessageList {
+            chat_id: 10,
+            range: (0, 6),
+            items: vec![
+                ChatItem::DayMarker(day(1)),
+                ChatItem::Message(1),
+                ChatItem::Message(2),
+                ChatItem::Message(3),
+                ChatItem::DayMarker(day(2)),
+                ChatItem::Message(4),
+            ],
+            messages: vec![
+                ChatMessage::DayMarker(day(1)),
+                message(1, 100, true),
+                message(2, 200, true),
+                message(3, 200, false),
+                ChatMessage::DayMarker(day(2)),
+                message(4, 100, true),
+            ],
+        };
+
+        assert_eq!(list.remove_message(99), None);
+
+        // the next message from the same sender now shows the sender
+        assert_eq!(list.remove_message(2), Some(vec![2, 3]));
+        assert_eq!(list.messages[2], message(3, 200, true));
+
+        // removing the only message of a day removes its marker
+        assert_eq!(list.remove_message(4), Some(vec![4]));
+        assert_eq!(list.messages.len(), 3);
+        assert_eq!(list.items.len(), 3);
+        assert_eq!(list.range, (0, 3));
+    }
+
+    #[test]
+    fn test_message_list_merge() {
+        let items: Vec<_> = (0..MAX_LOADED as u32 + 10).map(ChatItem::Message).collect();
+        let page = |start: usize, end: usize| MessageList {
+            chat_id: 10,
+            range: (start, end),
+            items: items.clone(),
+            messages: (start..end)
+                .map(|id| message(id as u32, 100, false))
+                .collect(),
+        };
+
+        let mut list = page(100, 150);
+        list.merge(page(50, 100));
+        assert_eq!(list.range, (50, 150));
+        assert_eq!(list.get(50), Some(&message(50, 100, false)));
+        assert_eq!(list.get(149), Some(&message(149, 100, false)));
+        assert_eq!(list.get(150), None);
+
+        // overlapping pages replace the loaded messages
+        list.merge(page(140, 160));
+        assert_eq!(list.range, (50, 160));
+        assert_eq!(list.messages.len(), 110);
+        assert_eq!(list.get(155), Some(&message(155, 100, false)));
+
+        // the messages furthest from the new page are dropped
+        list.merge(page(160, 210));
+        assert_eq!(list.range, (50, 210));
+        list.merge(page(0, 50));
+        assert_eq!(list.range, (0, MAX_LOADED));
+        list.merge(page(200, 210));
+        assert_eq!(list.range, (10, 210));
+        assert_eq!(list.messages.len(), MAX_LOADED);
+        assert_eq!(list.get(209), Some(&message(209, 100, false)));
+
+        // a page far away replaces everything
+        let mut list = page(0, 10);
+        list.merge(page(100, 110));
+        assert_eq!(list, page(100, 110));
+    }
+
+    fn chat(fresh_msg_cnt: usize, is_group: bool, is_contact_request: bool) -> ChatKind {
+        ChatKind {
+            is_group,
+            is_single: !is_group,
+            is_contact_request,
+            fresh_msg_cnt,
+        }
+    }
+
+    #[test]
+    fn test_chat_category_matches() {
+        let read_group = chat(0, true, false);
+        let unread_single = chat(3, false, false);
+        let request = chat(1, false, true);
+
+        let all = [&read_group, &unread_single, &request];
+        let matching =
+            |category: ChatCategory| all.iter().filter(|chat| category.matches(chat)).count();
+        assert_eq!(matching(ChatCategory::All), 3);
+        assert_eq!(matching(ChatCategory::Unread), 2);
+        assert_eq!(matching(ChatCategory::Groups), 1);
+        assert_eq!(matching(ChatCategory::Single), 2);
+        assert_eq!(matching(ChatCategory::Requests), 1);
+        assert!(ChatCategory::Groups.matches(&read_group));
+        assert!(!ChatCategory::Unread.matches(&read_group));
+        assert!(ChatCategory::Requests.matches(&request));
+    }
+
+    #[test]
+    fn test_progress_from_permille() {
+        assert_eq!(Progress::from_permille(0), Progress::Error);
+        assert_eq!(Progress::from_permille(1), Progress::Step(1));
+        assert_eq!(Progress::from_permille(450), Progress::Step(450));
+        assert_eq!(Progress::from_permille(999), Progress::Step(999));
+        assert_eq!(Progress::from_permille(1000), Progress::Success);
+    }
+
+    #[test]
+    fn test_viewtype_from_mime() {
+        assert_eq!(Viewtype::from_mime("image/gif"), Viewtype::Gif);
+        assert_eq!(Viewtype::from_mime("image/png"), Viewtype::Image);
+        assert_eq!(Viewtype::from_mime("video/mp4"), Viewtype::Video);
+        assert_eq!(Viewtype::from_mime("audio/ogg"), Viewtype::Audio);
+        assert_eq!(Viewtype::from_mime("application/pdf"), Viewtype::File);
+        assert_eq!(Viewtype::from_mime("garbage"), Viewtype::File);
+    }
 }
@@ -1,8 +1,10 @@
 use egui::Color32;
 use lazy_static::lazy_static;
 pub mod app;
+mod cache;
 mod dc;
 mod image;
+mod notifications;
 // mod scheduler;
 mod state;
 mod widgets;
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use futures::StreamExt;
+use log::warn;
+use tokio::{select, sync::mpsc};
+use zbus::{proxy, zvariant::Value, Connection};
+
+use super::{Notification, Notifier};
+
+/// The freedesktop.org notification service.
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+
+    #[zbus(signal)]
+    fn action_invoked(&self, id: u32, action_key: String) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn notification_closed(&self, id: u32, reason: u32) -> zbus::Result<()>;
+}
+
+/// Sends notifications over D-Bus, from a task that also listens for clicks.
+pub struct DbusNotifier {
+    notifications: mpsc::UnboundedSender<Notification>,
+}
+
+impl DbusNotifier {
+    pub fn new(on_click: impl Fn(u32, u32) + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            if let Err(err) = run(receiver, on_click).await {
+                warn!("desktop notifications are not available: {:#}", err);
+            }
+        });
+        DbusNotifier {
+            notifications: sender,
+        }
+    }
+}
+
+impl Notifier for DbusNotifier {
+    fn show(&mut self, notification: &Notification) -> Result<()> {
+        self.notifications
+            .send(notification.clone())
+            .map_err(|_| anyhow!("notification service stopped"))
+    }
+}
+
+async fn run(
+    mut notifications: mpsc::UnboundedReceiver<Notification>,
+    on_click: impl Fn(u32, u32),
+) -> Result<()> {
+    let connection = Connection::session().await?;
+    let proxy = NotificationsProxy::new(&connection).await?;
+    let mut clicks = proxy.receive_action_invoked().await?;
+    let mut closed = proxy.receive_notification_closed().await?;
+
+    // the account and chat of the notifications that are still shown
+    let mut shown: HashMap<u32, (u32, u32)> = HashMap::new();
+    loop {
+        select! {
+            notification = notifications.recv() => {
+                let notification = match notification {
+                    Some(notification) => notification,
+                    None => return Ok(()),
+                };
+                let res = proxy
+                    .notify(
+                        "Dreamer",
+                        0,
+                        "mail-message-new",
+                        &notification.title,
+                        &escape_markup(&notification.body),
+                        &["default", "Open"],
+                        HashMap::new(),
+                        -1,
+                    )
+                    .await;
+                match res {
+                    Ok(id) => {
+                        shown.insert(id, (notification.account, notification.chat));
+                    }
+                    Err(err) => warn!("failed to show notification: {:#}", err),
+                }
+            }
+            Some(signal) = clicks.next() => {
+                let args = signal.args()?;
+                if let Some((account, chat)) = shown.remove(&args.id) {
+                    on_click(account, chat);
+                }
+            }
+            Some(signal) = closed.next() => {
+                shown.remove(&signal.args()?.id);
+            }
+        }
+    }
+}
+
+/// Notification servers may interpret the body as markup.
+fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
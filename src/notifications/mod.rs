@@ -0,0 +1,188 @@
+//! Desktop notifications for incoming messages.
+
+#[cfg(target_os = "linux")]
+mod dbus;
+
+use anyhow::Result;
+use chrono::NaiveTime;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+/// A message the user is told about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub account: u32,
+    pub chat: u32,
+    pub title: String,
+    pub body: String,
+    /// The chat or the whole account is muted.
+    pub muted: bool,
+}
+
+/// Shows notifications to the user.
+pub trait Notifier: Send {
+    fn show(&mut self, notification: &Notification) -> Result<()>;
+}
+
+/// A daily time span without notifications, it may span midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DoNotDisturb {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl DoNotDisturb {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+impl Default for DoNotDisturb {
+    fn default() -> Self {
+        DoNotDisturb {
+            start: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+        }
+    }
+}
+
+pub struct Notifications {
+    notifier: Box<dyn Notifier>,
+}
+
+impl Notifications {
+    pub fn new(notifier: Box<dyn Notifier>) -> Self {
+        Notifications { notifier }
+    }
+
+    /// Uses the notifications of the desktop, `on_click` gets the account and chat of clicked ones.
+    ///
+    /// Must be called from within the tokio runtime.
+    pub fn for_desktop(on_click: impl Fn(u32, u32) + Send + 'static) -> Self {
+        #[cfg(target_os = "linux")]
+        let notifier = dbus::DbusNotifier::new(on_click);
+        #[cfg(not(target_os = "linux"))]
+        let notifier = {
+            drop(on_click);
+            Unsupported
+        };
+        Notifications::new(Box::new(notifier))
+    }
+
+    /// Shows the notification unless it is muted, it is do not disturb time or the user is
+    /// looking at the chat.
+    pub fn notify(
+        &mut self,
+        notification: &Notification,
+        viewing: Option<(u32, u32)>,
+        do_not_disturb: Option<DoNotDisturb>,
+        now: NaiveTime,
+    ) {
+        if notification.muted
+            || viewing == Some((notification.account, notification.chat))
+            || do_not_disturb.map_or(false, |dnd| dnd.contains(now))
+        {
+            return;
+        }
+        if let Err(err) = self.notifier.show(notification) {
+            warn!("failed to show notification: {:#}", err);
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+struct Unsupported;
+
+#[cfg(not(target_os = "linux"))]
+impl Notifier for Unsupported {
+    fn show(&mut self, _notification: &Notification) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    /// Keeps the notifications instead of showing them.
+    #[derive(Debug, Default, Clone)]
+    struct MemoryNotifier {
+        shown: Arc<Mutex<Vec<Notification>>>,
+    }
+
+    impl Notifier for MemoryNotifier {
+        fn show(&mut self, notification: &Notification) -> Result<()> {
+            self.shown.lock().unwrap().push(notification.clone());
+            Ok(())
+        }
+    }
+
+    fn time(hour: u32, min: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, min, 0).unwrap()
+    }
+
+    fn notification(account: u32, chat: u32, muted: bool) -> Notification {
+        Notification {
+            account,
+            chat,
+            title: "Alice".to_string(),
+            body: "hello".to_string(),
+            muted,
+        }
+    }
+
+    #[test]
+    fn test_notify() {
+        let notifier = MemoryNotifier::default();
+        let mut notifications = Notifications::new(Box::new(notifier.clone()));
+        let noon = time(12, 0);
+
+        notifications.notify(&notification(1, 10, false), None, None, noon);
+        // muted
+        notifications.notify(&notification(1, 11, true), None, None, noon);
+        // the chat is open
+        notifications.notify(&notification(1, 12, false), Some((1, 12)), None, noon);
+        // same chat id in another account
+        notifications.notify(&notification(2, 12, false), Some((1, 12)), None, noon);
+        // do not disturb
+        let dnd = DoNotDisturb {
+            start: time(11, 0),
+            end: time(13, 0),
+        };
+        notifications.notify(&notification(1, 13, false), None, Some(dnd), noon);
+
+        let shown: Vec<_> = notifier
+            .shown
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|n| (n.account, n.chat))
+            .collect();
+        assert_eq!(shown, vec![(1, 10), (2, 12)]);
+    }
+
+    #[test]
+    fn test_do_not_disturb() {
+        let day = DoNotDisturb {
+            start: time(9, 0),
+            end: time(17, 0),
+        };
+        assert!(!day.contains(time(8, 59)));
+        assert!(day.contains(time(9, 0)));
+        assert!(day.contains(time(16, 59)));
+        assert!(!day.contains(time(17, 0)));
+
+        let night = DoNotDisturb::default();
+        assert!(night.contains(time(23, 30)));
+        assert!(night.contains(time(0, 0)));
+        assert!(night.contains(time(6, 59)));
+        assert!(!night.contains(time(7, 0)));
+        assert!(!night.contains(time(12, 0)));
+    }
+}
@@ -1,61 +1,390 @@
-use std::collections::{BTreeMap, HashMap};
+use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
+use arc_swap::ArcSwap;
+
 use egui::{ColorImage, Context, TextureHandle};
 use log::{debug, error, info, warn};
 use tokio::runtime::Runtime;
-use tokio::{select, sync::RwLock};
+use tokio::select;
+use tokio::sync::{Mutex, MutexGuard, RwLock};
 
+use crate::cache::Lru;
 use crate::dc;
-use crate::dc::types::{ChatList, Event, Log, MessageList, SharedState};
+use crate::dc::types::{
+    ChatFilter, ChatItem, ChatList, ChatMessage, ChatState, Event, ImexMode, Log, Login,
+    MessageInfo, MessageList, Progress, SearchResult, ServerSettings, SharedState, Viewtype,
+};
+use crate::notifications::{DoNotDisturb, Notification, Notifications};
+use crate::widgets::{
+    chat_list::ChatListFilter,
+    chat_search::ChatSearch,
+    composer::Composer,
+    forward::ForwardDialog,
+    imex::ImexDialog,
+    mainpanel::{MessagePaging, MessageSelection},
+    message_info::MessageInfoDialog,
+    onboarding::LoginForm,
+    search::GlobalSearch,
+};
 //use crate::scheduler::Scheduler;
 
 #[derive(Clone)]
 pub struct AppState {
     rt: Arc<Runtime>,
-    shared_state: Arc<RwLock<State>>,
+    shared_state: Arc<Snapshot>,
 
     pub ui_cache: Arc<RwLock<UiCache>>,
 
     pub commands: tokio::sync::mpsc::Sender<Command>,
-    pub current_input: String,
+    pub composer: Composer,
+    /// Message the message list should scroll to once its chat is shown, as
+    /// `(account, chat, message)`.
+    pub scroll_to_message: Option<(u32, u32, u32)>,
+    pub selection: MessageSelection,
+    pub paging: MessagePaging,
+    pub login_form: LoginForm,
+    pub imex_dialog: ImexDialog,
+    pub forward_dialog: ForwardDialog,
+    pub message_info_dialog: MessageInfoDialog,
+    pub search: GlobalSearch,
+    pub chat_search: ChatSearch,
+    pub chat_list_filter: ChatListFilter,
 
-    pub image_cache: Arc<RwLock<HashMap<String, TextureHandle>>>,
+    /// Textures by name, limited to [`IMAGE_CACHE_BUDGET`] bytes.
+    pub image_cache: Arc<RwLock<Lru<String, TextureHandle>>>,
 }
 
-#[derive(Debug, Default)]
+/// Bytes of texture memory the image cache may use.
+const IMAGE_CACHE_BUDGET: usize = 256 * 1024 * 1024;
+
+/// Most row heights kept in the [`UiCache`].
+const MAX_ROW_HEIGHTS: usize = 10_000;
+
+/// Widths within a bucket share one cached height, the one of the latest width.
+const WIDTH_BUCKET: f32 = 16.;
+
+/// Key of a row height: account, chat, row and width bucket.
+type RowKey = (u32, u32, ChatItem, u32);
+
+#[derive(Debug)]
 pub struct UiCache {
-    message_heights: BTreeMap<u32, Vec<(f32, f32)>>,
+    /// Row heights of the message list, with the exact width they were calculated for.
+    message_heights: Lru<RowKey, (f32, f32)>,
 }
 
-impl UiCache {
-    pub fn get_message_height(&self, id: u32, width: f32) -> Option<f32> {
-        let sizes = self.message_heights.get(&id)?;
-        sizes.iter().find(|(w, _)| w == &width).map(|(_, h)| *h)
+impl Default for UiCache {
+    fn default() -> Self {
+        UiCache {
+            message_heights: Lru::new(MAX_ROW_HEIGHTS),
+        }
     }
+}
 
-    pub fn set_message_height(&mut self, id: u32, width: f32, height: f32) {
-        let entries = self.message_heights.entry(id).or_default();
-        if entries.iter().any(|(w, _)| w == &width) {
-            // already exists
-            return;
+impl UiCache {
+    pub fn get_message_height(
+        &mut self,
+        account: u32,
+        chat: u32,
+        item: &ChatItem,
+        width: f32,
+    ) -> Option<f32> {
+        let key = (account, chat, item.clone(), width_bucket(width));
+        match self.message_heights.get(&key) {
+            Some((w, height)) if *w == width => Some(*height),
+            _ => None,
         }
-        entries.push((width, height))
+    }
+
+    pub fn set_message_height(
+        &mut self,
+        account: u32,
+        chat: u32,
+        item: &ChatItem,
+        width: f32,
+        height: f32,
+    ) {
+        let key = (account, chat, item.clone(), width_bucket(width));
+        self.message_heights.insert(key, (width, height), 1);
+    }
+
+    /// Forgets the heights of a message whose content changed.
+    pub fn remove_message(&mut self, account: u32, chat: u32, id: u32) {
+        let item = ChatItem::Message(id);
+        self.message_heights
+            .retain(|key, _| !(key.0 == account && key.1 == chat && key.2 == item));
+    }
+
+    /// Forgets the heights of all rows of a chat.
+    pub fn remove_chat(&mut self, account: u32, chat: u32) {
+        self.message_heights
+            .retain(|key, _| !(key.0 == account && key.1 == chat));
     }
 }
 
+fn width_bucket(width: f32) -> u32 {
+    (width / WIDTH_BUCKET) as u32
+}
+
 #[derive(Debug)]
 pub enum Command {
     SelectChat(u32, u32),
     SelectAccount(u32),
-    SendTextMessage(String),
+    /// Sends text to the selected chat, optionally as reply to the given message.
+    SendTextMessage(String, Option<u32>),
+    /// Sends a file to the selected chat, `text` is used as its caption.
+    SendFileMessage {
+        typ: Viewtype,
+        path: String,
+        text: String,
+        mime: Option<String>,
+        quote: Option<u32>,
+    },
+    /// Adds a new account and configures it with the given credentials.
+    Login {
+        email: String,
+        password: String,
+        settings: ServerSettings,
+    },
+    /// Adds a new account from the backup at the given path.
+    ImportBackup(PathBuf),
+    AcceptContactRequest(u32, u32),
+    BlockContact(u32, u32),
+    DeleteChat(u32, u32),
+    PinChat(u32, u32),
+    UnpinChat(u32, u32),
+    ArchiveChat(u32, u32),
+    UnarchiveChat(u32, u32),
+    /// Turns notifications for a chat off or on.
+    MuteChat(u32, u32, bool),
+    /// Turns notifications for a whole account off or on.
+    MuteAccount(u32, bool),
+    SetDoNotDisturb(Option<DoNotDisturb>),
+    /// Searches messages of the selected account, or of all accounts.
+    Search {
+        query: String,
+        all_accounts: bool,
+    },
+    /// Searches the messages of a chat of an account.
+    SearchChat {
+        account: u32,
+        chat: u32,
+        query: String,
+    },
+    /// Shows the archived chats of the selected account instead of the normal ones.
+    ShowArchived(bool),
+    /// Shows only the chats matching the filter, in all accounts.
+    FilterChats(ChatFilter),
+    /// Loads more messages of the selected chat, see [`MessageList::merge`].
+    LoadMessages {
+        account: u32,
+        chat: u32,
+        range: (usize, usize),
+    },
+    /// Stores the text as draft of the chat, an empty text removes it.
+    SetDraft(u32, u32, String),
+    /// Sets our reaction to a message, an empty reaction removes it.
+    SendReaction(u32, u32, String),
+    /// Replaces the text of an own message of an account.
+    EditMessage(u32, u32, String),
+    /// Loads the details of a message of an account for its info dialog.
+    LoadMessageInfo(u32, u32),
+    /// Sends failed messages of an account again.
+    ResendMessages(u32, Vec<u32>),
+    /// Deletes messages of an account from this device and the server, with `true`
+    /// also for the other chat members.
+    DeleteMessages(u32, Vec<u32>, bool),
+    /// Loads the chats of an account messages can be forwarded to.
+    LoadForwardTargets(u32),
+    /// Forwards messages to a chat, which may belong to another account.
+    ForwardMessages {
+        from_account: u32,
+        msgs: Vec<u32>,
+        to_account: u32,
+        chat: u32,
+    },
+    /// Exports a backup or keys of an account, or imports keys into it.
+    Imex {
+        account: u32,
+        mode: ImexMode,
+        path: PathBuf,
+        passphrase: Option<String>,
+    },
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct State {
     pub shared_state: SharedState,
     pub message_list: MessageList,
     pub chat_list: ChatList,
+    pub login: Option<LoginAttempt>,
+    pub imex: Option<ImexAttempt>,
+    /// Draft of the selected chat, as loaded when it was selected.
+    pub draft: Option<Draft>,
+    /// Chats of an account messages can be forwarded to, as requested by the forward dialog.
+    pub forward_targets: Option<(u32, Vec<ChatState>)>,
+    /// Details of the message shown in the info dialog.
+    pub message_info: Option<MessageInfo>,
+    /// Daily time without notifications.
+    pub do_not_disturb: Option<DoNotDisturb>,
+    pub search: Option<SearchResults>,
+    /// Messages found by the last search in a chat.
+    pub chat_search: Option<ChatSearchResults>,
+}
+
+/// The [`State`] shown by the UI, replaced as a whole by the backend.
+///
+/// The UI loads the latest snapshot without waiting. The backend changes a copy of it,
+/// which is published once done, so its database queries never block a frame.
+pub struct Snapshot {
+    current: ArcSwap<State>,
+    /// Incremented with every published change.
+    version: AtomicU64,
+    /// Held while a copy is changed, so writers don't drop each other's changes.
+    writer: Mutex<()>,
+    ctx: Context,
+}
+
+impl Snapshot {
+    fn new(ctx: Context) -> Self {
+        Snapshot {
+            current: ArcSwap::from_pointee(State::default()),
+            version: AtomicU64::new(0),
+            writer: Mutex::new(()),
+            ctx,
+        }
+    }
+
+    pub fn load(&self) -> Arc<State> {
+        self.current.load_full()
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::Acquire)
+    }
+
+    /// Changes a copy of the state, published when the returned writer is dropped.
+    pub async fn write(&self) -> StateWriter<'_> {
+        let lock = self.writer.lock().await;
+        StateWriter {
+            state: State::clone(&self.current.load()),
+            snapshot: self,
+            _lock: lock,
+        }
+    }
+}
+
+/// A copy of the state being changed, see [`Snapshot::write`].
+pub struct StateWriter<'a> {
+    state: State,
+    snapshot: &'a Snapshot,
+    _lock: MutexGuard<'a, ()>,
+}
+
+impl Deref for StateWriter<'_> {
+    type Target = State;
+
+    fn deref(&self) -> &State {
+        &self.state
+    }
+}
+
+impl DerefMut for StateWriter<'_> {
+    fn deref_mut(&mut self) -> &mut State {
+        &mut self.state
+    }
+}
+
+impl Drop for StateWriter<'_> {
+    /// Publishes the copy and repaints, unless nothing changed.
+    fn drop(&mut self) {
+        if **self.snapshot.current.load() == self.state {
+            return;
+        }
+        let state = std::mem::take(&mut self.state);
+        self.snapshot.current.store(Arc::new(state));
+        self.snapshot.version.fetch_add(1, Ordering::AcqRel);
+        self.snapshot.ctx.request_repaint();
+    }
+}
+
+/// Status of the last login started from the onboarding screen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoginAttempt {
+    pub email: String,
+    pub account: Option<u32>,
+    pub status: Login,
+    /// Last configure progress step, kept on failure to show where it broke.
+    pub step: usize,
+}
+
+/// Status of the last backup or key import/export.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImexAttempt {
+    pub mode: ImexMode,
+    pub path: PathBuf,
+    pub account: Option<u32>,
+    pub status: Login,
+    /// Files written by an export.
+    pub output: Vec<PathBuf>,
+}
+
+/// Messages found by the last search.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResults {
+    pub query: String,
+    pub all_accounts: bool,
+    pub results: Vec<SearchResult>,
+}
+
+/// Messages of a chat found by the last search in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatSearchResults {
+    pub account: u32,
+    pub chat: u32,
+    pub query: String,
+    pub msg_ids: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Draft {
+    pub account: u32,
+    pub chat: u32,
+    pub text: String,
+}
+
+/// Loads the draft of the selected chat.
+async fn load_draft(dc_state: &dc::state::LocalState, s: &mut State) {
+    let selected = s
+        .shared_state
+        .selected_account
+        .zip(s.shared_state.selected_chat_id);
+    s.draft = match selected {
+        Some((account, chat)) => match dc_state.get_draft(account, chat).await {
+            Ok(text) => Some(Draft {
+                account,
+                chat,
+                text,
+            }),
+            Err(err) => {
+                warn!("failed to load draft: {:#}", err);
+                None
+            }
+        },
+        None => None,
+    };
+}
+
+/// Reloads the account state and the chat list after a chat was changed.
+async fn refresh_chats(dc_state: &dc::state::LocalState, s: &mut State) {
+    s.shared_state = dc_state.get_state().await;
+    s.chat_list = dc_state.load_chat_list(None).await.unwrap();
+    if s.shared_state.selected_chat_id.is_none() {
+        s.message_list.clear();
+    }
+    load_draft(dc_state, s).await;
 }
 
 impl AppState {
@@ -65,12 +394,16 @@ impl AppState {
         let (dc_events_sender, mut dc_events_receiver) = tokio::sync::mpsc::channel(1000);
         let (commands_sender, mut commands_receiver) = tokio::sync::mpsc::channel(1000);
 
-        let shared_state = Arc::new(RwLock::new(State::default()));
+        let shared_state = Arc::new(Snapshot::new(ctx.clone()));
         let rt = Arc::new(Runtime::new().unwrap());
 
+        let ui_cache: Arc<RwLock<UiCache>> = Default::default();
+
         let ss = shared_state.clone();
+        let cache = ui_cache.clone();
         let ctx = ctx.clone();
         let rt_local = rt.clone();
+        let commands = commands_sender.clone();
         rt.spawn(async move {
             let shared_state = ss;
             let dc_state = match dc::state::LocalState::new(rt_local).await {
@@ -94,19 +427,83 @@ impl AppState {
                     s.shared_state.selected_chat = info.chat;
                 }
 
-                s.chat_list = dc_state.load_chat_list(None).await.unwrap();
-                if let Some(_chat_id) = s.shared_state.selected_chat_id {
-                    s.message_list = dc_state.load_message_list(None).await.unwrap();
+                // on first start there is no account yet, onboarding takes care of that
+                if s.shared_state.selected_account.is_some() {
+                    s.chat_list = dc_state.load_chat_list(None).await.unwrap();
+                    if let Some(_chat_id) = s.shared_state.selected_chat_id {
+                        s.message_list = dc_state.load_message_list(None).await.unwrap();
+                    }
+                    load_draft(&dc_state, &mut s).await;
                 }
             }
 
-            ctx.request_repaint();
+
+            let mut notifications = {
+                let commands = commands.clone();
+                let ctx = ctx.clone();
+                Notifications::for_desktop(move |account, chat| {
+                    commands.try_send(Command::SelectAccount(account)).ok();
+                    commands.try_send(Command::SelectChat(account, chat)).ok();
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                })
+            };
 
             loop {
                 select! {
                     Some((account, event)) = dc_events_receiver.recv() => {
+                        if let Event::MessageIncoming { chat_id, title, body, is_muted } = &event {
+                            let s = shared_state.load();
+                            let account_muted = s
+                                .shared_state
+                                .accounts
+                                .get(&account)
+                                .map_or(false, |a| a.is_muted);
+                            let viewing = s
+                                .shared_state
+                                .selected_account
+                                .zip(s.shared_state.selected_chat_id)
+                                .filter(|_| ctx.input(|i| i.focused));
+                            let notification = Notification {
+                                account,
+                                chat: *chat_id,
+                                title: title.clone(),
+                                body: body.clone(),
+                                muted: *is_muted || account_muted,
+                            };
+                            let now = chrono::Local::now().time();
+                            notifications.notify(&notification, viewing, s.do_not_disturb, now);
+                        }
+
                         match event {
-                            Event::Configure(_progress) => {}
+                            Event::Configure(progress) => {
+                                let mut s = shared_state.write().await;
+                                if let Some(login) = s.login.as_mut() {
+                                    if let (Some(id), Progress::Step(step)) = (login.account, progress) {
+                                        if id == account {
+                                            login.status = Login::Progress(step);
+                                            login.step = step;
+                                        }
+                                    }
+                                }
+                            }
+                            Event::Imex(progress) => {
+                                let mut s = shared_state.write().await;
+                                if let Some(imex) = s.imex.as_mut() {
+                                    if let (Some(id), Progress::Step(step)) = (imex.account, progress) {
+                                        if id == account {
+                                            imex.status = Login::Progress(step);
+                                        }
+                                    }
+                                }
+                            }
+                            Event::ImexFileWritten(path) => {
+                                let mut s = shared_state.write().await;
+                                if let Some(imex) = s.imex.as_mut() {
+                                    if imex.account == Some(account) {
+                                        imex.output.push(path);
+                                    }
+                                }
+                            }
                             Event::Log(log) => match log {
                                 Log::Info(msg) => debug!("[{}] {}", account, msg),
                                 Log::Warning(msg) => warn!("[{}] {}", account, msg),
@@ -118,25 +515,79 @@ impl AppState {
                                 s.shared_state = dc_state.get_state().await;
                                 s.chat_list = dc_state.load_chat_list(None).await.unwrap();
                                 if s.shared_state.selected_chat_id.is_some() {
-                                    s.message_list = dc_state.load_message_list(None).await.unwrap();
+                                    let range = s.message_list.reload_range();
+                                    s.message_list = dc_state.load_message_list(range).await.unwrap();
                                 }
                             }
-                            Event::MessagesChanged { chat_id } | Event::MessageIncoming { chat_id, .. } => {
+                            Event::MessagesChanged { chat_id, .. } | Event::MessageIncoming { chat_id, .. } => {
                                 info!("new message list");
+                                if let Event::MessagesChanged { msg_id, .. } = event {
+                                    // changed messages are laid out again
+                                    let mut cache = cache.write().await;
+                                    match msg_id {
+                                        Some(msg_id) => cache.remove_message(account, chat_id, msg_id),
+                                        None => cache.remove_chat(account, chat_id),
+                                    }
+                                }
                                 let mut s = shared_state.write().await;
+                                // unread counts are shown for all accounts
+                                match dc_state.fresh_msg_count(account).await {
+                                    Ok(count) => {
+                                        if let Some(a) = s.shared_state.accounts.get_mut(&account) {
+                                            a.fresh_msg_cnt = count;
+                                        }
+                                    }
+                                    Err(err) => warn!("failed to count unread messages: {:#}", err),
+                                }
+                                if s.shared_state.selected_account != Some(account) {
+                                    continue;
+                                }
                                 s.chat_list = dc_state.load_chat_list(None).await.unwrap();
                                 if let Some(old_chat_id) = s.shared_state.selected_chat_id {
                                     if chat_id == old_chat_id {
-                                        s.message_list = dc_state.load_message_list(None).await.unwrap();
+                                        let range = s.message_list.reload_range();
+                                        s.message_list = dc_state.load_message_list(range).await.unwrap();
                                     }
                                 }
                             }
-                            _ => {
-                                // TODO: handle other events
+                            Event::MessageDeleted { chat_id, msg_id } => {
+                                let mut s = shared_state.write().await;
+                                let selected = s.shared_state.selected_account == Some(account)
+                                    && s.shared_state.selected_chat_id == Some(chat_id);
+                                if !selected {
+                                    continue;
+                                }
+                                if let Some(changed) = s.message_list.remove_message(msg_id) {
+                                    let mut cache = cache.write().await;
+                                    for id in changed {
+                                        cache.remove_message(account, chat_id, id);
+                                    }
+                                }
+                            }
+                            Event::ReactionsChanged { chat_id, msg_id } => {
+                                let mut s = shared_state.write().await;
+                                let selected = s.shared_state.selected_account == Some(account)
+                                    && s.shared_state.selected_chat_id == Some(chat_id);
+                                if !selected {
+                                    continue;
+                                }
+                                match dc_state.load_reactions(account, msg_id).await {
+                                    Ok(reactions) => {
+                                        if let Some(ChatMessage::Message(msg)) = s
+                                            .message_list
+                                            .messages
+                                            .iter_mut()
+                                            .find(|msg| msg.id() == Some(msg_id))
+                                        {
+                                            msg.reactions = reactions;
+                                            cache.write().await.remove_message(account, chat_id, msg_id);
+                                        }
+                                    }
+                                    Err(err) => warn!("failed to load reactions: {:#}", err),
+                                }
                             }
                         }
                         // TODO: be more selective on when to repaint
-                        ctx.request_repaint();
                     }
                     Some(cmd) = commands_receiver.recv() => {
                         match cmd {
@@ -144,8 +595,20 @@ impl AppState {
                                 let mut s = shared_state.write().await;
                                 s.message_list = dc_state.select_chat(account, chat).await.unwrap();
                                 s.shared_state = dc_state.get_state().await;
+                                load_draft(&dc_state, &mut s).await;
 
-                                ctx.request_repaint();
+                            }
+                            Command::LoadMessages { account, chat, range } => {
+                                let mut s = shared_state.write().await;
+                                let selected = s.shared_state.selected_account == Some(account)
+                                    && s.shared_state.selected_chat_id == Some(chat);
+                                if !selected {
+                                    continue;
+                                }
+                                match dc_state.load_message_list(Some(range)).await {
+                                    Ok(page) => s.message_list.merge(page),
+                                    Err(err) => warn!("failed to load messages: {:#}", err),
+                                }
                             }
                             Command::SelectAccount(account) => {
                                 info!("selecting account {}", account);
@@ -161,11 +624,320 @@ impl AppState {
                                 } else {
                                     s.message_list.clear();
                                 }
+                                load_draft(&dc_state, &mut s).await;
 
-                                ctx.request_repaint();
                             }
-                            Command::SendTextMessage(msg) => {
-                                dc_state.send_text_message(msg).await.unwrap();
+                            Command::SendTextMessage(msg, quote) => {
+                                if let Err(err) = dc_state.send_text_message(msg, quote).await {
+                                    warn!("failed to send message: {:#}", err);
+                                }
+                            }
+                            Command::ResendMessages(account, msgs) => {
+                                // the new state arrives with the resulting events
+                                if let Err(err) = dc_state.resend_messages(account, msgs).await {
+                                    warn!("failed to resend messages: {:#}", err);
+                                }
+                            }
+                            Command::DeleteMessages(account, msgs, for_all) => {
+                                // the list is updated from the resulting events
+                                if let Err(err) =
+                                    dc_state.delete_messages(account, msgs, for_all).await
+                                {
+                                    warn!("failed to delete messages: {:#}", err);
+                                }
+                            }
+                            Command::LoadForwardTargets(account) => {
+                                match dc_state.load_forward_targets(account).await {
+                                    Ok(chats) => {
+                                        shared_state.write().await.forward_targets =
+                                            Some((account, chats));
+                                    }
+                                    Err(err) => warn!("failed to load chats: {:#}", err),
+                                }
+                            }
+                            Command::ForwardMessages {
+                                from_account,
+                                msgs,
+                                to_account,
+                                chat,
+                            } => {
+                                if let Err(err) = dc_state
+                                    .forward_messages(from_account, msgs, to_account, chat)
+                                    .await
+                                {
+                                    warn!("failed to forward messages: {:#}", err);
+                                }
+                            }
+                            Command::LoadMessageInfo(account, msg) => {
+                                match dc_state.load_message_info(account, msg).await {
+                                    Ok(info) => {
+                                        shared_state.write().await.message_info = Some(info);
+                                    }
+                                    Err(err) => warn!("failed to load message info: {:#}", err),
+                                }
+                            }
+                            Command::SendReaction(account, msg, reaction) => {
+                                if let Err(err) = dc_state.send_reaction(account, msg, reaction).await {
+                                    warn!("failed to send reaction: {:#}", err);
+                                }
+                            }
+                            Command::EditMessage(account, msg, text) => {
+                                // the list is updated from the resulting events
+                                if let Err(err) = dc_state.edit_message(account, msg, text).await {
+                                    warn!("failed to edit message: {:#}", err);
+                                }
+                            }
+                            Command::SetDraft(account, chat, text) => {
+                                if let Err(err) = dc_state.set_draft(account, chat, text).await {
+                                    warn!("failed to store draft: {:#}", err);
+                                }
+                            }
+                            Command::SendFileMessage {
+                                typ,
+                                path,
+                                text,
+                                mime,
+                                quote,
+                            } => {
+                                let res = dc_state
+                                    .send_file_message(typ, path, text, mime, quote)
+                                    .await;
+                                if let Err(err) = res {
+                                    warn!("failed to send file: {:#}", err);
+                                }
+                            }
+                            Command::AcceptContactRequest(account, chat) => {
+                                let res = dc_state.accept_contact_request(account, chat).await;
+                                if let Err(err) = res {
+                                    warn!("failed to accept contact request: {:#}", err);
+                                }
+                                let mut s = shared_state.write().await;
+                                refresh_chats(&dc_state, &mut s).await;
+                            }
+                            Command::BlockContact(account, chat) => {
+                                if let Err(err) = dc_state.block_contact(account, chat).await {
+                                    warn!("failed to block contact: {:#}", err);
+                                }
+                                let mut s = shared_state.write().await;
+                                refresh_chats(&dc_state, &mut s).await;
+                            }
+                            Command::DeleteChat(account, chat) => {
+                                if let Err(err) = dc_state.delete_chat(account, chat).await {
+                                    warn!("failed to delete chat: {:#}", err);
+                                }
+                                let mut s = shared_state.write().await;
+                                refresh_chats(&dc_state, &mut s).await;
+                            }
+                            Command::PinChat(account, chat) => {
+                                if let Err(err) = dc_state.pin_chat(account, chat).await {
+                                    warn!("failed to pin chat: {:#}", err);
+                                }
+                                let mut s = shared_state.write().await;
+                                refresh_chats(&dc_state, &mut s).await;
+                            }
+                            Command::UnpinChat(account, chat) => {
+                                if let Err(err) = dc_state.unpin_chat(account, chat).await {
+                                    warn!("failed to unpin chat: {:#}", err);
+                                }
+                                let mut s = shared_state.write().await;
+                                refresh_chats(&dc_state, &mut s).await;
+                            }
+                            Command::MuteChat(account, chat, muted) => {
+                                if let Err(err) = dc_state.mute_chat(account, chat, muted).await {
+                                    warn!("failed to mute chat: {:#}", err);
+                                }
+                                let mut s = shared_state.write().await;
+                                refresh_chats(&dc_state, &mut s).await;
+                            }
+                            Command::MuteAccount(account, muted) => {
+                                if let Err(err) = dc_state.mute_account(account, muted).await {
+                                    warn!("failed to mute account: {:#}", err);
+                                }
+                                let mut s = shared_state.write().await;
+                                if let Some(a) = s.shared_state.accounts.get_mut(&account) {
+                                    a.is_muted = muted;
+                                }
+                            }
+                            Command::Search { query, all_accounts } => {
+                                let account = if all_accounts {
+                                    None
+                                } else {
+                                    shared_state.load().shared_state.selected_account
+                                };
+                                let results = match dc_state.search_messages(account, &query).await {
+                                    Ok(results) => results,
+                                    Err(err) => {
+                                        warn!("failed to search messages: {:#}", err);
+                                        Vec::new()
+                                    }
+                                };
+                                shared_state.write().await.search = Some(SearchResults {
+                                    query,
+                                    all_accounts,
+                                    results,
+                                });
+                            }
+                            Command::SearchChat { account, chat, query } => {
+                                let msg_ids = match dc_state.search_chat(account, chat, &query).await {
+                                    Ok(msg_ids) => msg_ids,
+                                    Err(err) => {
+                                        warn!("failed to search chat: {:#}", err);
+                                        Vec::new()
+                                    }
+                                };
+                                shared_state.write().await.chat_search = Some(ChatSearchResults {
+                                    account,
+                                    chat,
+                                    query,
+                                    msg_ids,
+                                });
+                            }
+                            Command::SetDoNotDisturb(do_not_disturb) => {
+                                shared_state.write().await.do_not_disturb = do_not_disturb;
+                            }
+                            Command::ArchiveChat(account, chat) => {
+                                if let Err(err) = dc_state.archive_chat(account, chat).await {
+                                    warn!("failed to archive chat: {:#}", err);
+                                }
+                                let mut s = shared_state.write().await;
+                                refresh_chats(&dc_state, &mut s).await;
+                            }
+                            Command::UnarchiveChat(account, chat) => {
+                                if let Err(err) = dc_state.unarchive_chat(account, chat).await {
+                                    warn!("failed to unarchive chat: {:#}", err);
+                                }
+                                let mut s = shared_state.write().await;
+                                refresh_chats(&dc_state, &mut s).await;
+                            }
+                            Command::ShowArchived(show) => {
+                                if let Err(err) = dc_state.show_archived(show).await {
+                                    warn!("failed to switch chat list: {:#}", err);
+                                }
+                                let mut s = shared_state.write().await;
+                                s.chat_list = dc_state.load_chat_list(None).await.unwrap();
+                            }
+                            Command::FilterChats(filter) => {
+                                dc_state.filter_chats(filter).await;
+                                let mut s = shared_state.write().await;
+                                s.chat_list = dc_state.load_chat_list(None).await.unwrap();
+                            }
+                            Command::Login { email, password, settings } => {
+                                info!("adding account {}", email);
+                                let (id, account_ctx) = match dc_state.add_account().await {
+                                    Ok(res) => res,
+                                    Err(err) => {
+                                        shared_state.write().await.login = Some(LoginAttempt {
+                                            email,
+                                            account: None,
+                                            status: Login::Error(err.to_string()),
+                                            step: 0,
+                                        });
+                                        continue;
+                                    }
+                                };
+                                shared_state.write().await.login = Some(LoginAttempt {
+                                    email: email.clone(),
+                                    account: Some(id),
+                                    status: Login::Progress(0),
+                                    step: 0,
+                                });
+
+                                // configuring takes a while, keep handling events in the meantime
+                                let dc_state = dc_state.clone();
+                                let shared_state = shared_state.clone();
+                                let commands = commands.clone();
+                                tokio::spawn(async move {
+                                    let res = dc_state
+                                        .login(id, &account_ctx, &email, &password, &settings)
+                                        .await;
+                                    let status = match res {
+                                        Ok(()) => {
+                                            commands.send(Command::SelectAccount(id)).await.ok();
+                                            Login::Success
+                                        }
+                                        Err(err) => {
+                                            warn!("failed to login {}: {:#}", email, err);
+                                            Login::Error(format!("{:#}", err))
+                                        }
+                                    };
+                                    if let Some(login) = shared_state.write().await.login.as_mut() {
+                                        if login.account == Some(id) {
+                                            login.status = status;
+                                        }
+                                    }
+                                });
+                            }
+                            Command::ImportBackup(path) => {
+                                info!("importing backup {}", path.display());
+                                let (id, account_ctx) = match dc_state.add_account().await {
+                                    Ok(res) => res,
+                                    Err(err) => {
+                                        shared_state.write().await.imex = Some(ImexAttempt {
+                                            mode: ImexMode::ImportBackup,
+                                            path,
+                                            account: None,
+                                            status: Login::Error(err.to_string()),
+                                            output: Vec::new(),
+                                        });
+                                        continue;
+                                    }
+                                };
+                                shared_state.write().await.imex = Some(ImexAttempt {
+                                    mode: ImexMode::ImportBackup,
+                                    path: path.clone(),
+                                    account: Some(id),
+                                    status: Login::Progress(0),
+                                    output: Vec::new(),
+                                });
+
+                                let dc_state = dc_state.clone();
+                                let shared_state = shared_state.clone();
+                                let commands = commands.clone();
+                                tokio::spawn(async move {
+                                    let status = match dc_state.import(&account_ctx, id, &path).await {
+                                        Ok(()) => {
+                                            commands.send(Command::SelectAccount(id)).await.ok();
+                                            Login::Success
+                                        }
+                                        Err(err) => {
+                                            warn!("failed to import {}: {:#}", path.display(), err);
+                                            Login::Error(format!("{:#}", err))
+                                        }
+                                    };
+                                    if let Some(imex) = shared_state.write().await.imex.as_mut() {
+                                        if imex.account == Some(id) {
+                                            imex.status = status;
+                                        }
+                                    }
+                                });
+                            }
+                            Command::Imex { account, mode, path, passphrase } => {
+                                info!("{:?} for account {} at {}", mode, account, path.display());
+                                shared_state.write().await.imex = Some(ImexAttempt {
+                                    mode,
+                                    path: path.clone(),
+                                    account: Some(account),
+                                    status: Login::Progress(0),
+                                    output: Vec::new(),
+                                });
+
+                                let dc_state = dc_state.clone();
+                                let shared_state = shared_state.clone();
+                                tokio::spawn(async move {
+                                    let res = dc_state.imex(account, mode, &path, passphrase).await;
+                                    let status = match res {
+                                        Ok(()) => Login::Success,
+                                        Err(err) => {
+                                            warn!("{:?} failed: {:#}", mode, err);
+                                            Login::Error(format!("{:#}", err))
+                                        }
+                                    };
+                                    if let Some(imex) = shared_state.write().await.imex.as_mut() {
+                                        if imex.account == Some(account) && imex.path == path {
+                                            imex.status = status;
+                                        }
+                                    }
+                                });
                             }
                         }
                     }
@@ -176,17 +948,33 @@ impl AppState {
         AppState {
             rt,
             shared_state,
-            ui_cache: Default::default(),
-            current_input: Default::default(),
+            ui_cache,
+            composer: Default::default(),
+            scroll_to_message: None,
+            selection: Default::default(),
+            paging: Default::default(),
+            login_form: Default::default(),
+            imex_dialog: Default::default(),
+            forward_dialog: Default::default(),
+            message_info_dialog: Default::default(),
+            search: Default::default(),
+            chat_search: Default::default(),
+            chat_list_filter: Default::default(),
             commands: commands_sender,
-            image_cache: Default::default(),
+            image_cache: Arc::new(RwLock::new(Lru::new(IMAGE_CACHE_BUDGET))),
         }
     }
 
     pub fn init(&mut self) {}
 
-    pub fn shared_state(&self) -> tokio::sync::RwLockReadGuard<'_, State> {
-        self.shared_state.blocking_read()
+    /// The latest state published by the backend.
+    pub fn shared_state(&self) -> Arc<State> {
+        self.shared_state.load()
+    }
+
+    /// Changes whenever the backend publishes a new state.
+    pub fn state_version(&self) -> u64 {
+        self.shared_state.version()
     }
 
     pub fn send_command(&self, cmd: Command) {
@@ -206,7 +994,7 @@ impl AppState {
     {
         let image_cache = self.image_cache.clone();
         let name2 = name.clone();
-        let val = image_cache.blocking_read().get(&name2).cloned();
+        let val = image_cache.blocking_write().get(&name2).cloned();
 
         if val.is_none() {
             // Lazy load
@@ -222,7 +1010,8 @@ impl AppState {
                         })
                         .await
                         .unwrap();
-                        image_cache.write().await.insert(name, texture);
+                        let cost = texture.byte_size();
+                        image_cache.write().await.insert(name, texture, cost);
                         ctx.request_repaint();
                     }
                     Err(err) => {
@@ -1,13 +1,26 @@
-use egui::{Color32, CursorIcon, Frame, Rounding, ScrollArea, SidePanel, Stroke, Ui, Vec2};
+use chrono::{NaiveTime, Timelike};
+use egui::{
+    Color32, CursorIcon, Frame, RichText, Rounding, ScrollArea, SidePanel, Stroke, Ui, Vec2,
+};
 
 use crate::{
+    notifications::DoNotDisturb,
     state::{AppState, Command},
     ACCENT_COLOR,
 };
 
-use super::avatar::Avatar;
+use super::{avatar::Avatar, imex};
+
+/// Actions that need mutable access to the state, applied after rendering.
+enum Action {
+    AddAccount,
+    ExportBackup(u32),
+    ExportKeys(u32),
+    ImportKeys(u32),
+}
 
-pub fn render(ui: &mut Ui, state: &AppState) {
+pub fn render(ui: &mut Ui, state: &mut AppState) {
+    let mut action = None;
     let shared_state = state.shared_state();
     let accounts = &shared_state.shared_state.accounts;
     SidePanel::left("accountlist")
@@ -36,7 +49,8 @@ pub fn render(ui: &mut Ui, state: &AppState) {
                         let response = ui.add(
                             Avatar::new(name.to_string(), Vec2::splat(40.), fill)
                                 .stroke(Stroke::new(1., Color32::WHITE))
-                                .rounding(Rounding::same(5.)),
+                                .rounding(Rounding::same(5.))
+                                .badge(account.fresh_msg_cnt),
                         );
                         if response.clicked() {
                             state.send_command(Command::SelectAccount(*id));
@@ -44,8 +58,112 @@ pub fn render(ui: &mut Ui, state: &AppState) {
                         if response.hovered() {
                             ui.output_mut(|o| o.cursor_icon = CursorIcon::PointingHand);
                         }
+                        response.context_menu(|ui| {
+                            if ui.button("Export backup…").clicked() {
+                                action = Some(Action::ExportBackup(*id));
+                                ui.close_menu();
+                            }
+                            if ui.button("Export keys…").clicked() {
+                                action = Some(Action::ExportKeys(*id));
+                                ui.close_menu();
+                            }
+                            if ui.button("Import keys…").clicked() {
+                                action = Some(Action::ImportKeys(*id));
+                                ui.close_menu();
+                            }
+                            ui.separator();
+                            let label = if account.is_muted {
+                                "Unmute notifications"
+                            } else {
+                                "Mute notifications"
+                            };
+                            if ui.button(label).clicked() {
+                                state.send_command(Command::MuteAccount(*id, !account.is_muted));
+                                ui.close_menu();
+                            }
+                        });
                     });
                 }
+
+                ui.add_space(10.);
+                ui.vertical_centered(|ui| {
+                    let response = ui.add(
+                        egui::Button::new(RichText::new("+").size(24.).color(Color32::WHITE))
+                            .min_size(Vec2::splat(40.))
+                            .fill(Color32::TRANSPARENT)
+                            .stroke(Stroke::new(1., Color32::WHITE))
+                            .rounding(Rounding::same(5.)),
+                    );
+                    if response.on_hover_text("Add account").clicked() {
+                        action = Some(Action::AddAccount);
+                    }
+                });
+
+                ui.add_space(10.);
+                ui.vertical_centered(|ui| {
+                    view_do_not_disturb(ui, state, shared_state.do_not_disturb);
+                });
             });
         });
+    drop(shared_state);
+
+    match action {
+        Some(Action::AddAccount) => state.login_form.open = true,
+        Some(Action::ExportBackup(id)) => imex::export_backup(state, id),
+        Some(Action::ExportKeys(id)) => imex::export_keys(state, id),
+        Some(Action::ImportKeys(id)) => imex::import_keys(state, id),
+        None => {}
+    }
+}
+
+/// Button with a popup to set up the daily time without notifications.
+fn view_do_not_disturb(ui: &mut Ui, state: &AppState, do_not_disturb: Option<DoNotDisturb>) {
+    let icon = if do_not_disturb.is_some() {
+        "🔕"
+    } else {
+        "🔔"
+    };
+    let response = ui.add(
+        egui::Button::new(RichText::new(icon).size(20.).color(Color32::WHITE))
+            .min_size(Vec2::splat(40.))
+            .fill(Color32::TRANSPARENT)
+            .stroke(Stroke::new(1., Color32::WHITE))
+            .rounding(Rounding::same(5.)),
+    );
+    let response = response.on_hover_text("Do not disturb");
+    let popup_id = ui.make_persistent_id("do-not-disturb");
+    if response.clicked() {
+        ui.memory_mut(|mem| mem.toggle_popup(popup_id));
+    }
+
+    egui::popup::popup_below_widget(ui, popup_id, &response, |ui| {
+        ui.set_min_width(180.);
+        let mut enabled = do_not_disturb.is_some();
+        let mut dnd = do_not_disturb.unwrap_or_default();
+        let before = (enabled, dnd);
+
+        ui.checkbox(&mut enabled, "Do not disturb");
+        ui.add_enabled_ui(enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("From");
+                hour_picker(ui, &mut dnd.start);
+                ui.label("to");
+                hour_picker(ui, &mut dnd.end);
+            });
+        });
+
+        if (enabled, dnd) != before {
+            state.send_command(Command::SetDoNotDisturb(Some(dnd).filter(|_| enabled)));
+        }
+    });
+}
+
+fn hour_picker(ui: &mut Ui, time: &mut NaiveTime) {
+    let mut hour = time.hour();
+    ui.add(
+        egui::DragValue::new(&mut hour)
+            .clamp_range(0..=23)
+            .custom_formatter(|hour, _| format!("{:02}:00", hour)),
+    );
+    *time = NaiveTime::from_hms_opt(hour, 0, 0).unwrap_or(*time);
 }
@@ -17,6 +17,7 @@ pub struct Avatar {
     stroke: Stroke,
     image: Option<Image<'static>>,
     sense: Sense,
+    badge: usize,
 }
 
 impl Avatar {
@@ -30,6 +31,7 @@ impl Avatar {
             stroke: Stroke::NONE,
             image: None,
             sense: Sense::hover().union(Sense::click()),
+            badge: 0,
         }
     }
 
@@ -48,6 +50,12 @@ impl Avatar {
         self
     }
 
+    /// Shows a count in the top right corner, nothing if it is 0.
+    pub fn badge(mut self, count: usize) -> Self {
+        self.badge = count;
+        self
+    }
+
     pub fn image(mut self, texture: Option<TextureHandle>) -> Self {
         let sized = texture.map(|t| SizedTexture::new(&t, t.size_vec2()));
         self.image = sized.map(Image::from_texture);
@@ -106,6 +114,34 @@ impl Widget for Avatar {
 
             ui.painter()
                 .rect_stroke(rect.expand2(expansion), self.rounding, stroke);
+
+            if self.badge > 0 {
+                let text = if self.badge > 99 {
+                    "99+".to_string()
+                } else {
+                    self.badge.to_string()
+                };
+                let galley = WidgetText::RichText(
+                    RichText::new(text)
+                        .size(10.)
+                        .family(egui::FontFamily::Name(FONT_SEMI_BOLD.into()))
+                        .color(Color32::WHITE),
+                )
+                .into_galley(ui, Some(false), f32::INFINITY, TextStyle::Small);
+                let size = Vec2::new((galley.size().x + 6.).max(16.), 16.);
+                let badge_rect = egui::Rect::from_center_size(rect.right_top(), size)
+                    .translate(Vec2::new(-4., 4.));
+                ui.painter().rect_filled(
+                    badge_rect,
+                    Rounding::same(8.),
+                    Color32::from_rgb(220, 50, 50),
+                );
+                ui.painter().galley(
+                    badge_rect.center() - galley.size() / 2.,
+                    galley,
+                    Color32::WHITE,
+                );
+            }
         }
         response
     }
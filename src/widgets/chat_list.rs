@@ -1,34 +1,74 @@
 use std::borrow::Cow;
 
-use egui::{Color32, Frame, RichText, Rounding, ScrollArea, Sense, SidePanel, Stroke, Ui, Vec2};
+use egui::{
+    Color32, Frame, RichText, Rounding, ScrollArea, Sense, SidePanel, Stroke, TextEdit, Ui, Vec2,
+};
 
 use epaint::Margin;
 
 use crate::{
     app::{FONT_REGULAR, FONT_SEMI_BOLD},
-    dc::types::{ChatState, SharedState},
+    dc::types::{ChatCategory, ChatFilter, ChatState, SharedState},
     image,
     state::{AppState, Command},
+    ACCENT_COLOR,
 };
 
 use super::avatar::Avatar;
 
-pub fn render(ui: &mut Ui, state: &AppState) {
+/// The filter being edited above the chat list.
+#[derive(Debug, Default, Clone)]
+pub struct ChatListFilter {
+    filter: ChatFilter,
+    /// Scroll position of the unfiltered list, restored once the filter is cleared.
+    offset: f32,
+    restore_offset: bool,
+}
+
+const CATEGORIES: [(ChatCategory, &str); 5] = [
+    (ChatCategory::All, "All"),
+    (ChatCategory::Unread, "Unread"),
+    (ChatCategory::Groups, "Groups"),
+    (ChatCategory::Single, "1:1"),
+    (ChatCategory::Requests, "Requests"),
+];
+
+pub fn render(ui: &mut Ui, state: &mut AppState) {
     SidePanel::right("chatlist")
         .frame(Frame::default().inner_margin(2.))
         .min_width(280.)
         .max_width(280.)
         .resizable(false)
         .show_inside(ui, |ui| {
-            ScrollArea::vertical()
-                .auto_shrink([false; 2])
-                .show(ui, |ui| {
+            view_filter(ui, state);
+
+            let unfiltered = state.chat_list_filter.filter == ChatFilter::default();
+            let mut scroll_area = ScrollArea::vertical()
+                .id_source("chatlist-scroll")
+                .auto_shrink([false; 2]);
+            // the unfiltered chats need to be loaded before the position can be restored
+            let loaded = state.shared_state().chat_list.filter == ChatFilter::default();
+            if unfiltered && loaded && state.chat_list_filter.restore_offset {
+                state.chat_list_filter.restore_offset = false;
+                scroll_area = scroll_area.vertical_scroll_offset(state.chat_list_filter.offset);
+            }
+
+            let output = {
+                let state: &AppState = state;
+                scroll_area.show(ui, |ui| {
                     ui.vertical(|ui| {
                         let shared_state = state.shared_state();
                         let chats = &shared_state.chat_list;
 
                         let chat_len = chats.chats.len();
 
+                        if chats.archived {
+                            view_archive_link(ui, state, "⬅ Back to chats", false);
+                        } else if chats.archived_len > 0 {
+                            let label = format!("🗄 Archived chats ({})", chats.archived_len);
+                            view_archive_link(ui, state, &label, true);
+                        }
+
                         for (i, chat) in chats.chats.iter().enumerate() {
                             let bg_color =
                                 if Some(chat.id) == shared_state.shared_state.selected_chat_id {
@@ -57,9 +97,38 @@ pub fn render(ui: &mut Ui, state: &AppState) {
                         }
                     });
                 })
+            };
+            if unfiltered && loaded {
+                state.chat_list_filter.offset = output.state.offset.y;
+            }
         });
 }
 
+fn view_filter(ui: &mut Ui, state: &mut AppState) {
+    let filter = &mut state.chat_list_filter;
+    let before = filter.filter.clone();
+
+    ui.add(
+        TextEdit::singleline(&mut filter.filter.query)
+            .hint_text("Filter chats")
+            .desired_width(f32::INFINITY),
+    );
+    ui.horizontal(|ui| {
+        ui.spacing_mut().item_spacing.x = 2.;
+        for (category, label) in CATEGORIES {
+            ui.selectable_value(&mut filter.filter.category, category, label);
+        }
+    });
+    ui.add_space(2.);
+
+    if filter.filter != before {
+        // the offset of the unfiltered list was saved in the last frame
+        filter.restore_offset = filter.filter == ChatFilter::default();
+        let cmd = Command::FilterChats(filter.filter.clone());
+        state.send_command(cmd);
+    }
+}
+
 fn truncate(text: &String, len: usize) -> Cow<'_, String> {
     if text.len() <= len {
         Cow::Borrowed(text)
@@ -71,6 +140,22 @@ fn truncate(text: &String, len: usize) -> Cow<'_, String> {
     }
 }
 
+/// A small pill with a label, next to the chat name.
+fn badge(ui: &mut Ui, text: &str, fill: Color32) {
+    Frame::none()
+        .fill(fill)
+        .rounding(Rounding::same(8.))
+        .inner_margin(Margin::symmetric(6., 1.))
+        .show(ui, |ui| {
+            ui.label(
+                RichText::new(text)
+                    .family(egui::FontFamily::Name(FONT_SEMI_BOLD.into()))
+                    .size(11.)
+                    .color(Color32::WHITE),
+            );
+        });
+}
+
 fn view_chat(ui: &mut Ui, state: &AppState, shared_state: &SharedState, chat: &ChatState) {
     let response = ui
         .add_enabled_ui(true, |ui| {
@@ -98,25 +183,108 @@ fn view_chat(ui: &mut Ui, state: &AppState, shared_state: &SharedState, chat: &C
                 );
 
                 ui.vertical(|ui| {
-                    ui.label(
-                        RichText::new(truncate(&chat.name, 20).as_ref())
-                            .family(egui::FontFamily::Name(FONT_SEMI_BOLD.into()))
-                            .size(14.),
-                    );
-
-                    ui.label(
-                        RichText::new(truncate(&chat.preview, 30).as_ref())
-                            .family(egui::FontFamily::Name(FONT_REGULAR.into()))
-                            .size(14.),
-                    );
+                    ui.horizontal(|ui| {
+                        ui.label(
+                            RichText::new(truncate(&chat.name, 20).as_ref())
+                                .family(egui::FontFamily::Name(FONT_SEMI_BOLD.into()))
+                                .size(14.),
+                        );
+                        if chat.is_pinned {
+                            ui.label(RichText::new("📌").size(12.))
+                                .on_hover_text("Pinned");
+                        }
+                        if chat.is_muted {
+                            ui.label(RichText::new("🔕").size(12.))
+                                .on_hover_text("Muted");
+                        }
+                        if chat.is_contact_request {
+                            badge(ui, "Request", *ACCENT_COLOR);
+                        }
+                        if chat.fresh_msg_cnt > 0 {
+                            badge(
+                                ui,
+                                &chat.fresh_msg_cnt.to_string(),
+                                Color32::from_rgb(220, 50, 50),
+                            );
+                        }
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.spacing_mut().item_spacing.x = 4.;
+                        if !chat.header.is_empty() {
+                            let color = if chat.is_draft {
+                                Color32::DARK_RED
+                            } else {
+                                Color32::DARK_GRAY
+                            };
+                            ui.label(
+                                RichText::new(format!("{}:", chat.header))
+                                    .family(egui::FontFamily::Name(FONT_SEMI_BOLD.into()))
+                                    .size(14.)
+                                    .color(color),
+                            );
+                        }
+                        let len = 30usize.saturating_sub(chat.header.chars().count());
+                        ui.label(
+                            RichText::new(truncate(&chat.preview, len).as_ref())
+                                .family(egui::FontFamily::Name(FONT_REGULAR.into()))
+                                .size(14.),
+                        );
+                    });
                 });
             })
         })
         .response;
 
     let response = response.interact(Sense::click());
+    let account = match shared_state.selected_account {
+        Some(account) => account,
+        None => return,
+    };
     if response.clicked() {
-        let account = shared_state.selected_account.unwrap();
         state.send_command(Command::SelectChat(account, chat.id));
     }
+    response.context_menu(|ui| {
+        let item = |ui: &mut Ui, label: &str, command: Command| {
+            if ui.button(label).clicked() {
+                state.send_command(command);
+                ui.close_menu();
+            }
+        };
+        if chat.is_pinned {
+            item(ui, "Unpin", Command::UnpinChat(account, chat.id));
+        } else if !chat.is_archived {
+            item(ui, "Pin", Command::PinChat(account, chat.id));
+        }
+        if chat.is_archived {
+            item(ui, "Unarchive", Command::UnarchiveChat(account, chat.id));
+        } else {
+            item(ui, "Archive", Command::ArchiveChat(account, chat.id));
+        }
+        if chat.is_muted {
+            item(ui, "Unmute", Command::MuteChat(account, chat.id, false));
+        } else {
+            item(ui, "Mute", Command::MuteChat(account, chat.id, true));
+        }
+    });
+}
+
+/// Row switching between the normal and the archived chats.
+fn view_archive_link(ui: &mut Ui, state: &AppState, label: &str, show_archived: bool) {
+    let response = egui::Frame::none()
+        .inner_margin(Margin::same(5.0))
+        .show(ui, |ui| {
+            ui.set_width(280.);
+            ui.label(
+                RichText::new(label)
+                    .family(egui::FontFamily::Name(FONT_SEMI_BOLD.into()))
+                    .size(14.),
+            );
+        })
+        .response
+        .interact(Sense::click());
+    if response.clicked() {
+        state.send_command(Command::ShowArchived(show_archived));
+    }
+    ui.separator();
 }
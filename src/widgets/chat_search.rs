@@ -0,0 +1,238 @@
+use std::collections::HashSet;
+use std::time::Instant;
+
+use egui::{Context, Id, Key, KeyboardShortcut, Modifiers, RichText, TextEdit, Ui};
+
+use crate::{
+    dc::types::{ChatItem, ChatMessage, MessageList},
+    state::{AppState, Command},
+};
+
+use super::search::{find_matches, SEARCH_DELAY};
+
+const SEARCH_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::F);
+
+const SEARCH_ID: &str = "chat-search";
+
+/// The search within the selected chat, opened with Ctrl+F.
+#[derive(Debug, Default, Clone)]
+pub struct ChatSearch {
+    open: bool,
+    /// The `(account, chat)` searched in.
+    chat: Option<(u32, u32)>,
+    query: String,
+    edited: Option<Instant>,
+    /// Matching messages, the newest first.
+    matches: Vec<u32>,
+    /// What the matches were found for: the query, the chat and loaded range of the
+    /// message list and the number of found messages.
+    matched: Option<(String, u32, (usize, usize), usize)>,
+    /// Index of the shown match.
+    current: usize,
+}
+
+impl ChatSearch {
+    /// The text to highlight in the messages, empty while the search is closed.
+    pub fn highlight(&self) -> &str {
+        if self.open {
+            self.query.trim()
+        } else {
+            ""
+        }
+    }
+}
+
+/// Opens the search on Ctrl+F and resets it when another chat gets selected.
+pub fn update_chat_search(ctx: &Context, state: &mut AppState, selected_chat: Option<(u32, u32)>) {
+    let search = &mut state.chat_search;
+    if search.chat != selected_chat {
+        *search = ChatSearch {
+            chat: selected_chat,
+            ..Default::default()
+        };
+    }
+    if selected_chat.is_some() && ctx.input_mut(|i| i.consume_shortcut(&SEARCH_SHORTCUT)) {
+        search.open = true;
+        ctx.memory_mut(|mem| mem.request_focus(Id::new(SEARCH_ID)));
+    }
+}
+
+/// Renders the search bar above the messages, if the search is open.
+pub fn render_chat_search(ui: &mut Ui, state: &mut AppState) {
+    let (account, chat) = match state.chat_search.chat {
+        Some(chat) if state.chat_search.open => chat,
+        _ => return,
+    };
+    update_matches(state, account, chat);
+
+    // `Some(true)` goes to the next older match, `Some(false)` to the next newer one
+    let mut step = None;
+    let mut close = false;
+    ui.horizontal(|ui| {
+        let search = &mut state.chat_search;
+        let response = ui.add(
+            TextEdit::singleline(&mut search.query)
+                .id(Id::new(SEARCH_ID))
+                .hint_text("🔍 Search in chat")
+                .desired_width(250.),
+        );
+        if response.changed() {
+            search.edited = Some(Instant::now());
+        }
+        if response.lost_focus() {
+            ui.input(|i| {
+                if i.key_pressed(Key::Escape) {
+                    close = true;
+                } else if i.key_pressed(Key::Enter) {
+                    step = Some(!i.modifiers.shift);
+                }
+            });
+            if step.is_some() {
+                // keep typing or pressing enter
+                response.request_focus();
+            }
+        }
+
+        let searching = search.edited.is_some();
+        if search.matches.is_empty() {
+            if !search.query.trim().is_empty() && !searching {
+                ui.label(RichText::new("No matches").weak());
+            }
+        } else {
+            ui.label(format!(
+                "{} of {}",
+                search.current + 1,
+                search.matches.len()
+            ));
+        }
+        if searching {
+            ui.spinner();
+        }
+
+        let enabled = search.matches.len() > 1;
+        if ui
+            .add_enabled(enabled, egui::Button::new("⏶"))
+            .on_hover_text("Older match (Enter)")
+            .clicked()
+        {
+            step = Some(true);
+        }
+        if ui
+            .add_enabled(enabled, egui::Button::new("⏷"))
+            .on_hover_text("Newer match (Shift+Enter)")
+            .clicked()
+        {
+            step = Some(false);
+        }
+        if ui.button("✖").on_hover_text("Close (Esc)").clicked() {
+            close = true;
+        }
+    });
+
+    let search = &mut state.chat_search;
+    if close {
+        search.open = false;
+        return;
+    }
+    if let Some(older) = step {
+        let len = search.matches.len();
+        if len > 0 {
+            search.current = if older {
+                (search.current + 1) % len
+            } else {
+                (search.current + len - 1) % len
+            };
+            state.scroll_to_message = Some((account, chat, search.matches[search.current]));
+        }
+    }
+
+    // messages which aren't loaded are only found by deltachat
+    if let Some(edited) = search.edited {
+        let elapsed = edited.elapsed();
+        if elapsed >= SEARCH_DELAY {
+            search.edited = None;
+            let query = search.query.trim().to_string();
+            if !query.is_empty() {
+                state.send_command(Command::SearchChat {
+                    account,
+                    chat,
+                    query,
+                });
+            }
+        } else {
+            ui.ctx().request_repaint_after(SEARCH_DELAY - elapsed);
+        }
+    }
+}
+
+/// Finds the matches again when the query or the messages changed.
+///
+/// A new query starts at the newest match, otherwise the shown match is kept.
+fn update_matches(state: &mut AppState, account: u32, chat: u32) {
+    let query = state.chat_search.query.trim().to_string();
+    let (key, matches) = {
+        let shared_state = state.shared_state();
+        let list = &shared_state.message_list;
+        let found = shared_state
+            .chat_search
+            .as_ref()
+            .filter(|found| (found.account, found.chat) == (account, chat) && found.query == query)
+            .map_or(&[][..], |found| &found.msg_ids[..]);
+
+        let key = (query, list.chat_id, list.range, found.len());
+        if state.chat_search.matched.as_ref() == Some(&key) {
+            return;
+        }
+        let matches = find_chat_matches(list, found, &key.0);
+        (key, matches)
+    };
+
+    let search = &mut state.chat_search;
+    let same_query = search
+        .matched
+        .as_ref()
+        .map_or(false, |(query, ..)| *query == key.0);
+    let current = search
+        .matches
+        .get(search.current)
+        .copied()
+        .filter(|_| same_query)
+        .and_then(|id| matches.iter().position(|m| *m == id));
+    search.matched = Some(key);
+    search.matches = matches;
+
+    match current {
+        Some(current) => search.current = current,
+        None => {
+            search.current = 0;
+            if let Some(id) = search.matches.first() {
+                state.scroll_to_message = Some((account, chat, *id));
+            }
+        }
+    }
+}
+
+/// The messages of the list matching `query`, the newest first.
+///
+/// Loaded messages are matched by their text, the ones `found` by deltachat are
+/// added to also cover the history that isn't loaded.
+fn find_chat_matches(list: &MessageList, found: &[u32], query: &str) -> Vec<u32> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let mut ids: HashSet<u32> = found.iter().copied().collect();
+    ids.extend(list.messages.iter().filter_map(|msg| match msg {
+        ChatMessage::Message(msg) if !find_matches(&msg.text, query).is_empty() => Some(msg.id),
+        _ => None,
+    }));
+
+    list.items
+        .iter()
+        .rev()
+        .filter_map(|item| match item {
+            ChatItem::Message(id) if ids.contains(id) => Some(*id),
+            _ => None,
+        })
+        .collect()
+}
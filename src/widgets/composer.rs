@@ -0,0 +1,538 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use log::warn;
+
+use egui::{
+    load::SizedTexture, Color32, Context, Frame, Id, Key, KeyboardShortcut, LayerId, Modifiers,
+    Order, RichText, Rounding, TextEdit, Ui, Vec2,
+};
+use epaint::Margin;
+
+use crate::{
+    app::FONT_REGULAR,
+    dc::types::{InnerChatMessage, Viewtype},
+    image,
+    state::{AppState, Command},
+};
+
+/// How long typing has to pause before the draft is stored.
+const DRAFT_DELAY: Duration = Duration::from_secs(1);
+
+const COMPOSER_ID: &str = "composer-input";
+
+/// Input for new messages of the selected chat.
+#[derive(Debug, Clone)]
+pub struct Composer {
+    /// The `(account, chat)` the text belongs to.
+    chat: Option<(u32, u32)>,
+    pub text: String,
+    /// Files staged in the composer, sent together with `text`.
+    pub attachments: Vec<Attachment>,
+    /// The message this is a reply to.
+    pub quote: Option<InnerChatMessage>,
+    /// The own message whose text is edited and the text typed before, which is
+    /// restored once the edit is sent or cancelled.
+    editing: Option<(u32, String)>,
+    /// Enter sends the message and Shift+Enter adds a newline, otherwise
+    /// Enter adds a newline and Ctrl+Enter sends.
+    pub enter_sends: bool,
+    /// When `text` was last changed without being stored as draft.
+    edited: Option<Instant>,
+    /// Whether V is held down without the paste shortcut.
+    plain_v_down: bool,
+}
+
+impl Default for Composer {
+    fn default() -> Self {
+        Composer {
+            chat: None,
+            text: String::new(),
+            attachments: Vec::new(),
+            quote: None,
+            editing: None,
+            enter_sends: true,
+            edited: None,
+            plain_v_down: false,
+        }
+    }
+}
+
+/// A file staged in the composer, sent with the next message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attachment {
+    pub path: PathBuf,
+    pub viewtype: Viewtype,
+    pub mime: Option<String>,
+}
+
+impl Attachment {
+    pub fn from_path(path: PathBuf) -> Self {
+        let mime = mime_guess::from_path(&path)
+            .first()
+            .map(|mime| mime.essence_str().to_string());
+        let viewtype = mime
+            .as_deref()
+            .map(Viewtype::from_mime)
+            .unwrap_or(Viewtype::File);
+
+        Attachment {
+            path,
+            viewtype,
+            mime,
+        }
+    }
+
+    fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+}
+
+/// Stores the text as draft of its chat and loads the draft of the selected chat
+/// when the selection changed.
+pub fn update_draft(ctx: &Context, state: &mut AppState) {
+    let (selected, draft) = {
+        let shared_state = state.shared_state();
+        let selected = shared_state
+            .shared_state
+            .selected_account
+            .zip(shared_state.shared_state.selected_chat_id);
+        let draft = shared_state
+            .draft
+            .as_ref()
+            .filter(|draft| Some((draft.account, draft.chat)) == selected)
+            .map(|draft| draft.text.clone());
+        (selected, draft)
+    };
+
+    if selected != state.composer.chat {
+        save_draft(state);
+        state.composer.chat = selected;
+        state.composer.text = draft.unwrap_or_default();
+        state.composer.attachments.clear();
+        state.composer.quote = None;
+        state.composer.editing = None;
+        return;
+    }
+
+    if let Some(edited) = state.composer.edited {
+        let elapsed = edited.elapsed();
+        if elapsed >= DRAFT_DELAY {
+            save_draft(state);
+        } else {
+            ctx.request_repaint_after(DRAFT_DELAY - elapsed);
+        }
+    }
+}
+
+fn save_draft(state: &mut AppState) {
+    // the text of an edited message isn't a draft
+    if state.composer.edited.take().is_none() || state.composer.editing.is_some() {
+        return;
+    }
+    if let Some((account, chat)) = state.composer.chat {
+        let text = state.composer.text.clone();
+        state.send_command(Command::SetDraft(account, chat, text));
+    }
+}
+
+/// Moves the keyboard focus to the message input.
+pub fn focus_composer(ctx: &Context) {
+    ctx.memory_mut(|m| m.request_focus(Id::new(COMPOSER_ID)));
+}
+
+/// Loads the text of an own message into the composer to edit it.
+pub fn edit_message(state: &mut AppState, msg: &InnerChatMessage) {
+    save_draft(state);
+    let previous = match state.composer.editing.take() {
+        Some((_, previous)) => previous,
+        None => std::mem::take(&mut state.composer.text),
+    };
+    state.composer.text = msg.text.clone();
+    state.composer.editing = Some((msg.id, previous));
+}
+
+/// Renders the input for new messages, including staged attachments.
+pub fn render_composer(ui: &mut Ui, state: &mut AppState) {
+    receive_dropped_files(ui, state);
+    receive_pasted_image(ui, state);
+
+    // the reply and attachments are kept for the next message
+    if state.composer.editing.is_some() {
+        view_editing(ui, state);
+    } else {
+        if state.composer.quote.is_some() {
+            view_quote(ui, state);
+        }
+
+        if !state.composer.attachments.is_empty() {
+            view_attachments(ui, state);
+        }
+    }
+
+    ui.horizontal(|ui| {
+        let attach = ui
+            .button(RichText::new("📎").size(16.))
+            .on_hover_text("Attach files");
+        if attach.clicked() {
+            let files = rfd::FileDialog::new()
+                .set_title("Attach files")
+                .pick_files()
+                .unwrap_or_default();
+            state
+                .composer
+                .attachments
+                .extend(files.into_iter().map(Attachment::from_path));
+        }
+
+        let enter_sends = state.composer.enter_sends;
+        ui.menu_button(RichText::new("⚙").size(16.), |ui| {
+            ui.checkbox(&mut state.composer.enter_sends, "Send with Enter");
+        })
+        .response
+        .on_hover_text("Composer settings");
+
+        let (return_key, hint) = if enter_sends {
+            (
+                KeyboardShortcut::new(Modifiers::SHIFT, Key::Enter),
+                "Message, Shift+Enter for a new line",
+            )
+        } else {
+            (
+                KeyboardShortcut::new(Modifiers::NONE, Key::Enter),
+                "Message, Ctrl+Enter to send",
+            )
+        };
+
+        egui::ScrollArea::vertical()
+            .id_source("composer")
+            .max_height(150.)
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                let response = ui.add(
+                    TextEdit::multiline(&mut state.composer.text)
+                        .id(Id::new(COMPOSER_ID))
+                        .desired_rows(1)
+                        .desired_width(f32::INFINITY)
+                        .hint_text(hint)
+                        .return_key(return_key),
+                );
+                if response.changed() {
+                    state.composer.edited = Some(Instant::now());
+                }
+
+                let submitted = response.has_focus()
+                    && ui.input(|i| {
+                        i.key_pressed(Key::Enter)
+                            && if enter_sends {
+                                !i.modifiers.shift
+                            } else {
+                                i.modifiers.command
+                            }
+                    });
+                if submitted {
+                    send(state);
+                }
+            });
+    });
+}
+
+/// Sends the staged attachments and text, the text becomes the caption of the first attachment.
+///
+/// While editing, only the text is sent as the new text of the edited message.
+fn send(state: &mut AppState) {
+    if let Some((id, previous)) = state.composer.editing.take() {
+        let text = std::mem::replace(&mut state.composer.text, previous);
+        if let (Some((account, _)), false) = (state.composer.chat, text.trim().is_empty()) {
+            state.send_command(Command::EditMessage(account, id, text));
+        }
+        return;
+    }
+
+    let mut message = std::mem::take(&mut state.composer.text);
+    let attachments = std::mem::take(&mut state.composer.attachments);
+    let mut quote = state.composer.quote.take().map(|quote| quote.id);
+
+    // the sent text must not come back as draft
+    state.composer.edited = Some(Instant::now());
+    save_draft(state);
+
+    if attachments.is_empty() {
+        if !message.trim().is_empty() {
+            state.send_command(Command::SendTextMessage(message, quote));
+        }
+        return;
+    }
+
+    for attachment in attachments {
+        state.send_command(Command::SendFileMessage {
+            typ: attachment.viewtype,
+            path: attachment.path.to_string_lossy().to_string(),
+            text: std::mem::take(&mut message),
+            mime: attachment.mime,
+            quote: quote.take(),
+        });
+    }
+}
+
+fn receive_dropped_files(ui: &mut Ui, state: &mut AppState) {
+    let (hovering, dropped) = ui.input(|i| {
+        (
+            !i.raw.hovered_files.is_empty(),
+            i.raw
+                .dropped_files
+                .iter()
+                .filter_map(|file| file.path.clone())
+                .collect::<Vec<_>>(),
+        )
+    });
+
+    if hovering {
+        // cover the whole chat, dropping works anywhere in the window
+        let painter = ui.ctx().layer_painter(LayerId::new(
+            Order::Foreground,
+            Id::new("attachment-drop-target"),
+        ));
+        let rect = ui.ctx().screen_rect();
+        painter.rect_filled(rect, 0., Color32::from_black_alpha(160));
+        painter.text(
+            rect.center(),
+            egui::Align2::CENTER_CENTER,
+            "Drop files to attach them",
+            egui::FontId::new(20., egui::FontFamily::Name(FONT_REGULAR.into())),
+            Color32::WHITE,
+        );
+    }
+
+    state
+        .composer
+        .attachments
+        .extend(dropped.into_iter().map(Attachment::from_path));
+}
+
+/// Stages an image from the clipboard when the paste shortcut is used in the composer.
+///
+/// egui only forwards pasted text and swallows the key press of the shortcut, so
+/// the clipboard is checked for image data when V is released without having
+/// been pressed, or when the paste key is released.
+fn receive_pasted_image(ui: &mut Ui, state: &mut AppState) {
+    let mut pasted = false;
+    ui.input(|i| {
+        for event in &i.events {
+            match event {
+                egui::Event::Key {
+                    key: Key::V,
+                    pressed,
+                    ..
+                } => {
+                    pasted |= !pressed && !state.composer.plain_v_down;
+                    state.composer.plain_v_down = *pressed;
+                }
+                egui::Event::Key {
+                    key: Key::Paste,
+                    pressed: false,
+                    ..
+                } => pasted = true,
+                _ => {}
+            }
+        }
+    });
+    if !pasted || !ui.memory(|m| m.has_focus(Id::new(COMPOSER_ID))) {
+        return;
+    }
+
+    let blobdir = {
+        let shared_state = state.shared_state();
+        let shared_state = &shared_state.shared_state;
+        shared_state
+            .selected_account
+            .and_then(|id| shared_state.accounts.get(&id))
+            .map(|account| account.blobdir.clone())
+    };
+    let blobdir = match blobdir {
+        Some(blobdir) => blobdir,
+        None => return,
+    };
+
+    match paste_image(&blobdir) {
+        Ok(Some(attachment)) => state.composer.attachments.push(attachment),
+        Ok(None) => {}
+        Err(err) => warn!("failed to paste image: {:#}", err),
+    }
+}
+
+/// Writes the image in the clipboard, if any, to a PNG file in `blobdir`.
+fn paste_image(blobdir: &Path) -> Result<Option<Attachment>> {
+    let image = match arboard::Clipboard::new()?.get_image() {
+        Ok(image) => image,
+        Err(arboard::Error::ContentNotAvailable) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    let buffer = ::image::RgbaImage::from_raw(
+        image.width as u32,
+        image.height as u32,
+        image.bytes.into_owned(),
+    )
+    .ok_or_else(|| anyhow!("invalid image data in clipboard"))?;
+
+    let name = format!(
+        "pasted-{}.png",
+        chrono::Utc::now().format("%Y%m%d-%H%M%S%.3f")
+    );
+    let path = blobdir.join(name);
+    buffer.save_with_format(&path, ::image::ImageFormat::Png)?;
+
+    Ok(Some(Attachment {
+        path,
+        viewtype: Viewtype::Image,
+        mime: Some("image/png".into()),
+    }))
+}
+
+/// Shows the message that is replied to, with a button to cancel the reply.
+fn view_quote(ui: &mut Ui, state: &mut AppState) {
+    let mut cancel = false;
+    let quote = state.composer.quote.as_ref().unwrap();
+    Frame::none()
+        .fill(Color32::WHITE)
+        .rounding(Rounding::same(4.))
+        .inner_margin(Margin::same(4.))
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(RichText::new("↩").size(16.));
+                if matches!(quote.viewtype, Viewtype::Image | Viewtype::Gif) {
+                    if let Some(path) = quote.file.clone() {
+                        let id = format!("attachment-{}", path.display());
+                        let image = state.get_or_load_image(ui.ctx(), id, move |_name| {
+                            image::load_image_from_path(&path)
+                        });
+                        if let Some(image) = image {
+                            let [width, height] = image.size();
+                            let scale = 32. / height.max(1) as f32;
+                            let size = Vec2::new(width as f32, height as f32) * scale.min(1.);
+                            ui.image(SizedTexture::new(image.id(), size));
+                        }
+                    }
+                }
+                ui.vertical(|ui| {
+                    ui.label(RichText::new(&quote.from_first_name).size(12.).strong());
+                    let snippet = quote.text.lines().next().unwrap_or_default();
+                    let snippet = if snippet.chars().count() > 80 {
+                        format!("{} ...", snippet.chars().take(80).collect::<String>())
+                    } else {
+                        snippet.to_string()
+                    };
+                    ui.label(RichText::new(snippet).size(12.));
+                });
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    cancel = ui.small_button("✖").on_hover_text("Cancel reply").clicked();
+                });
+            });
+        });
+
+    if cancel {
+        state.composer.quote = None;
+    }
+}
+
+fn view_editing(ui: &mut Ui, state: &mut AppState) {
+    let mut cancel = false;
+    Frame::none()
+        .fill(Color32::WHITE)
+        .rounding(Rounding::same(4.))
+        .inner_margin(Margin::same(4.))
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(RichText::new("✏").size(16.));
+                ui.label(RichText::new("Edit message").size(12.).strong());
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    cancel = ui
+                        .small_button("✖")
+                        .on_hover_text("Cancel editing")
+                        .clicked();
+                });
+            });
+        });
+
+    if cancel {
+        if let Some((_, previous)) = state.composer.editing.take() {
+            state.composer.text = previous;
+        }
+    }
+}
+
+fn view_attachments(ui: &mut Ui, state: &mut AppState) {
+    let mut remove = None;
+    egui::ScrollArea::horizontal()
+        .id_source("attachments")
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                for (i, attachment) in state.composer.attachments.iter().enumerate() {
+                    Frame::none()
+                        .fill(Color32::WHITE)
+                        .rounding(Rounding::same(4.))
+                        .inner_margin(Margin::same(4.))
+                        .show(ui, |ui| {
+                            ui.vertical(|ui| {
+                                ui.set_width(100.);
+                                view_attachment_preview(ui, state, attachment);
+                                ui.horizontal(|ui| {
+                                    ui.label(RichText::new(attachment.file_name()).size(11.))
+                                        .on_hover_text(attachment.path.display().to_string());
+                                    if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                                        remove = Some(i);
+                                    }
+                                });
+                            });
+                        });
+                }
+            });
+        });
+
+    if let Some(i) = remove {
+        state.composer.attachments.remove(i);
+    }
+}
+
+fn view_attachment_preview(ui: &mut Ui, state: &AppState, attachment: &Attachment) {
+    let size = Vec2::new(100., 70.);
+    match attachment.viewtype {
+        Viewtype::Image | Viewtype::Gif => {
+            let path = attachment.path.clone();
+            let id = format!("attachment-{}", path.display());
+            let image = state.get_or_load_image(ui.ctx(), id, move |_name| {
+                image::load_image_from_path(&path)
+            });
+            if let Some(image) = image {
+                let [width, height] = image.size();
+                let scale = (size.x / width as f32).min(size.y / height as f32).min(1.);
+                let image_size = Vec2::new(width as f32, height as f32) * scale;
+                ui.image(SizedTexture::new(image.id(), image_size));
+            } else {
+                ui.add_space(size.y);
+            }
+        }
+        _ => {
+            ui.allocate_ui(size, |ui| {
+                ui.centered_and_justified(|ui| {
+                    ui.label(RichText::new(file_icon(attachment.viewtype)).size(32.));
+                });
+            });
+        }
+    }
+}
+
+fn file_icon(viewtype: Viewtype) -> &'static str {
+    match viewtype {
+        Viewtype::Audio | Viewtype::Voice => "🎵",
+        Viewtype::Video => "🎞",
+        _ => "📄",
+    }
+}
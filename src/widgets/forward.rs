@@ -0,0 +1,123 @@
+use egui::{Align2, ComboBox, Context, ScrollArea, TextEdit, Window};
+
+use crate::state::{AppState, Command};
+
+/// Messages waiting for the user to pick the chat they are forwarded to.
+#[derive(Debug, Default, Clone)]
+pub struct ForwardDialog {
+    forward: Option<Forward>,
+}
+
+#[derive(Debug, Clone)]
+struct Forward {
+    from_account: u32,
+    msgs: Vec<u32>,
+    /// The account whose chats are offered, may differ from `from_account`.
+    to_account: u32,
+    filter: String,
+}
+
+/// Opens the dialog to forward messages of `account`.
+pub fn forward_messages(state: &mut AppState, account: u32, msgs: Vec<u32>) {
+    state.forward_dialog.forward = Some(Forward {
+        from_account: account,
+        msgs,
+        to_account: account,
+        filter: String::new(),
+    });
+    state.send_command(Command::LoadForwardTargets(account));
+}
+
+pub fn render_forward_dialog(ctx: &Context, state: &mut AppState) {
+    let mut forward = match state.forward_dialog.forward.clone() {
+        Some(forward) => forward,
+        None => return,
+    };
+
+    let mut accounts: Vec<_> = state
+        .shared_state()
+        .shared_state
+        .accounts
+        .iter()
+        .map(|(id, account)| {
+            let name = account
+                .display_name
+                .clone()
+                .unwrap_or_else(|| account.email.clone());
+            (*id, name)
+        })
+        .collect();
+    accounts.sort_unstable_by_key(|(id, _)| *id);
+
+    let mut target = None;
+    let mut open = true;
+    Window::new("Forward to…")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, [0., 0.])
+        .open(&mut open)
+        .show(ctx, |ui| {
+            ui.set_width(300.);
+
+            if accounts.len() > 1 {
+                let selected = accounts
+                    .iter()
+                    .find(|(id, _)| *id == forward.to_account)
+                    .map(|(_, name)| name.clone())
+                    .unwrap_or_default();
+                let previous = forward.to_account;
+                ComboBox::from_label("Account")
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        for (id, name) in &accounts {
+                            ui.selectable_value(&mut forward.to_account, *id, name);
+                        }
+                    });
+                if forward.to_account != previous {
+                    state.send_command(Command::LoadForwardTargets(forward.to_account));
+                }
+            }
+
+            ui.add(
+                TextEdit::singleline(&mut forward.filter)
+                    .hint_text("Search chats")
+                    .desired_width(f32::INFINITY),
+            );
+            ui.add_space(5.);
+
+            let shared_state = state.shared_state();
+            let chats = match &shared_state.forward_targets {
+                Some((account, chats)) if *account == forward.to_account => chats,
+                _ => {
+                    ui.spinner();
+                    return;
+                }
+            };
+            let filter = forward.filter.to_lowercase();
+            ScrollArea::vertical().max_height(300.).show(ui, |ui| {
+                for chat in chats
+                    .iter()
+                    .filter(|chat| chat.name.to_lowercase().contains(&filter))
+                {
+                    let button = egui::Button::new(&chat.name).frame(false);
+                    if ui.add_sized([ui.available_width(), 24.], button).clicked() {
+                        target = Some(chat.id);
+                    }
+                }
+            });
+        });
+
+    if let Some(chat) = target {
+        state.send_command(Command::ForwardMessages {
+            from_account: forward.from_account,
+            msgs: forward.msgs,
+            to_account: forward.to_account,
+            chat,
+        });
+        state.forward_dialog.forward = None;
+    } else if !open {
+        state.forward_dialog.forward = None;
+    } else {
+        state.forward_dialog.forward = Some(forward);
+    }
+}
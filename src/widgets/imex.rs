@@ -0,0 +1,208 @@
+use std::path::PathBuf;
+
+use egui::{Align2, Color32, Context, ProgressBar, RichText, TextEdit, Window};
+
+use crate::{
+    dc::types::{ImexMode, Login},
+    state::{AppState, Command},
+};
+
+/// The import or export whose progress is shown, if any.
+#[derive(Debug, Default, Clone)]
+pub struct ImexDialog {
+    running: Option<(ImexMode, PathBuf)>,
+    export: Option<ExportForm>,
+}
+
+/// Asks for the passphrase before exporting a backup.
+#[derive(Debug, Clone)]
+struct ExportForm {
+    account: u32,
+    passphrase: String,
+}
+
+/// Lets the user pick a backup file and starts importing it into a new account.
+pub fn import_backup(state: &mut AppState) {
+    let path = rfd::FileDialog::new()
+        .set_title("Import backup")
+        .add_filter("Delta Chat backup", &["tar"])
+        .pick_file();
+
+    if let Some(path) = path {
+        state.imex_dialog.running = Some((ImexMode::ImportBackup, path.clone()));
+        state.send_command(Command::ImportBackup(path));
+    }
+}
+
+/// Asks for an optional passphrase and a folder, then exports a backup of `account`.
+pub fn export_backup(state: &mut AppState, account: u32) {
+    state.imex_dialog.export = Some(ExportForm {
+        account,
+        passphrase: String::new(),
+    });
+}
+
+/// Exports the keys of `account` into a folder picked by the user.
+pub fn export_keys(state: &mut AppState, account: u32) {
+    let path = rfd::FileDialog::new()
+        .set_title("Export keys")
+        .pick_folder();
+
+    if let Some(path) = path {
+        start(state, account, ImexMode::ExportSelfKeys, path, None);
+    }
+}
+
+/// Imports keys into `account` from a file picked by the user.
+pub fn import_keys(state: &mut AppState, account: u32) {
+    let path = rfd::FileDialog::new()
+        .set_title("Import keys")
+        .add_filter("OpenPGP key", &["asc"])
+        .pick_file();
+
+    if let Some(path) = path {
+        start(state, account, ImexMode::ImportSelfKeys, path, None);
+    }
+}
+
+fn start(
+    state: &mut AppState,
+    account: u32,
+    mode: ImexMode,
+    path: PathBuf,
+    passphrase: Option<String>,
+) {
+    state.imex_dialog.running = Some((mode, path.clone()));
+    state.send_command(Command::Imex {
+        account,
+        mode,
+        path,
+        passphrase,
+    });
+}
+
+pub fn render_imex_dialog(ctx: &Context, state: &mut AppState) {
+    render_export_form(ctx, state);
+
+    let (mode, path) = match state.imex_dialog.running.clone() {
+        Some(running) => running,
+        None => return,
+    };
+    let (status, output) = state
+        .shared_state()
+        .imex
+        .as_ref()
+        .filter(|imex| imex.mode == mode && imex.path == path)
+        .map(|imex| (imex.status.clone(), imex.output.clone()))
+        .unwrap_or((Login::Progress(0), Vec::new()));
+
+    if mode == ImexMode::ImportBackup && status == Login::Success {
+        // the backend selects the imported account
+        state.imex_dialog = ImexDialog::default();
+        state.login_form = Default::default();
+        return;
+    }
+
+    let title = match mode {
+        ImexMode::ImportBackup => "Importing backup",
+        ImexMode::ExportBackup => "Exporting backup",
+        ImexMode::ExportSelfKeys => "Exporting keys",
+        ImexMode::ImportSelfKeys => "Importing keys",
+    };
+
+    let mut open = true;
+    Window::new(title)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, [0., 0.])
+        .show(ctx, |ui| {
+            ui.set_width(300.);
+            ui.label(path.display().to_string());
+            ui.add_space(5.);
+
+            match &status {
+                Login::Progress(step) => {
+                    ui.add(
+                        ProgressBar::new(*step as f32 / 1000.)
+                            .show_percentage()
+                            .animate(true),
+                    );
+                }
+                Login::Error(err) => {
+                    ui.label(RichText::new(err.as_str()).color(Color32::DARK_RED));
+                }
+                Login::Success => {
+                    if output.is_empty() {
+                        ui.label("Done.");
+                    } else {
+                        ui.label("Saved to:");
+                        for file in &output {
+                            ui.label(RichText::new(file.display().to_string()).monospace());
+                        }
+                    }
+                }
+                Login::Not => {}
+            }
+
+            if !matches!(status, Login::Progress(_)) {
+                ui.add_space(5.);
+                if ui.button("Close").clicked() {
+                    open = false;
+                }
+            }
+        });
+
+    if !open {
+        state.imex_dialog.running = None;
+    }
+}
+
+fn render_export_form(ctx: &Context, state: &mut AppState) {
+    let form = match state.imex_dialog.export.as_mut() {
+        Some(form) => form,
+        None => return,
+    };
+
+    let mut export = false;
+    let mut open = true;
+    Window::new("Export backup")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, [0., 0.])
+        .show(ctx, |ui| {
+            ui.set_width(300.);
+            ui.label("Optionally protect the backup with a passphrase.");
+            ui.add(
+                TextEdit::singleline(&mut form.passphrase)
+                    .hint_text("Passphrase")
+                    .password(true)
+                    .desired_width(f32::INFINITY),
+            );
+            ui.add_space(5.);
+            ui.horizontal(|ui| {
+                export = ui.button("Choose folder…").clicked();
+                if ui.button("Cancel").clicked() {
+                    open = false;
+                }
+            });
+        });
+
+    if export {
+        let form = state.imex_dialog.export.take().unwrap();
+        let path = rfd::FileDialog::new()
+            .set_title("Export backup")
+            .pick_folder();
+        if let Some(path) = path {
+            let passphrase = Some(form.passphrase).filter(|p| !p.is_empty());
+            start(
+                state,
+                form.account,
+                ImexMode::ExportBackup,
+                path,
+                passphrase,
+            );
+        }
+    } else if !open {
+        state.imex_dialog.export = None;
+    }
+}
@@ -1,64 +1,142 @@
+use std::collections::BTreeSet;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use egui::{
-    load::SizedTexture, CentralPanel, Color32, Context, Frame, Response, RichText, Rounding,
-    TextEdit, TopBottomPanel, Ui, Vec2, Widget,
+    load::SizedTexture, style::Spacing, CentralPanel, Color32, Context, Frame, Rect, Response,
+    RichText, Rounding, Sense, Style, TextEdit, TextFormat, TextStyle, TopBottomPanel, Ui, Vec2,
+    Widget,
 };
 use egui_extras::{Column, TableBuilder};
-use epaint::{FontId, Margin, Stroke, TextureHandle};
+use epaint::{text::Fonts, FontId, Galley, Margin, Stroke, TextureHandle};
 use log::{info, warn};
 
 use crate::{
     app::{FONT_LIGHT, FONT_REGULAR, FONT_SEMI_BOLD},
-    dc::types::{ChatMessage, InnerChatMessage, SharedState, Viewtype},
+    dc::types::{
+        ChatItem, ChatMessage, ChatState, InnerChatMessage, MessageList, MessageState, SharedState,
+        Viewtype, PAGE_SIZE,
+    },
     image,
     state::{AppState, Command},
     ACCENT_COLOR,
 };
 
-use super::avatar::Avatar;
+use super::{
+    avatar::Avatar,
+    chat_search::{render_chat_search, update_chat_search},
+    composer::{edit_message, focus_composer, render_composer, update_draft},
+    forward::forward_messages,
+    message_info::show_message_info,
+    search::{find_matches, highlighted},
+};
+
+/// Messages selected to act on several at once.
+#[derive(Debug, Default, Clone)]
+pub struct MessageSelection {
+    /// The `(account, chat)` the selected messages belong to.
+    chat: Option<(u32, u32)>,
+    ids: BTreeSet<u32>,
+    /// Messages of an account waiting for the deletion to be confirmed, `true` to
+    /// delete them for everyone.
+    confirm_delete: Option<(u32, Vec<u32>, bool)>,
+}
+
+/// Height of a row whose message isn't loaded yet.
+const PLACEHOLDER_HEIGHT: f32 = 40.;
+
+/// How long a requested page may take before another one is requested, so a failed
+/// request doesn't leave the rows loading forever.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+const TEXT_COLOR: Color32 = Color32::from_rgb(41, 51, 63);
+
+const AVATAR_SIZE: f32 = 40.;
+
+/// Space left of messages without avatar, showing the time when hovered.
+const TIME_WIDTH: f32 = 48.;
+
+/// Margin around the text of [`selectable_text`].
+const TEXT_MARGIN: Margin = Margin::symmetric(4., 2.);
+
+/// Space left of quoted messages.
+const QUOTE_INDENT: f32 = 10.;
+
+/// The pages of the message list around the shown rows.
+#[derive(Debug, Default, Clone)]
+pub struct MessagePaging {
+    /// Chat and loaded range of the list in the last frame.
+    loaded: Option<(u32, (usize, usize))>,
+    /// First and last row shown in the last frame.
+    visible: Option<(usize, usize)>,
+    /// When a page was requested from the backend, until the list changes or the
+    /// request times out.
+    requested: Option<Instant>,
+}
 
 pub fn render_main_panel(ctx: &Context, state: &mut AppState) {
+    update_draft(ctx, state);
+
+    let selected_chat = {
+        let shared_state = state.shared_state();
+        shared_state
+            .shared_state
+            .selected_account
+            .zip(shared_state.shared_state.selected_chat_id)
+    };
+    if state.selection.chat != selected_chat {
+        state.selection.chat = selected_chat;
+        state.selection.ids.clear();
+    }
+    update_chat_search(ctx, state, selected_chat);
+
+    let mut action = None;
     CentralPanel::default()
         .frame(Frame::default().fill(Color32::WHITE))
         .show(ctx, |ui| {
-            let can_send = state
-                .shared_state()
-                .shared_state
-                .selected_chat
-                .clone()
+            let (account, chat) = {
+                let shared_state = state.shared_state();
+                (
+                    shared_state.shared_state.selected_account,
+                    shared_state.shared_state.selected_chat.clone(),
+                )
+            };
+            let can_send = chat
+                .as_ref()
                 .map(|chat_state| chat_state.can_send)
                 .unwrap_or(false);
-            // show the input-field for new messages
-            if can_send {
+            if let (Some(account), Some(chat)) = (account, &chat) {
+                if chat.is_contact_request {
+                    // requests need a decision before anything can be sent
+                    TopBottomPanel::bottom("contact-request")
+                        .frame(
+                            Frame::default()
+                                .fill(Color32::LIGHT_GRAY)
+                                .inner_margin(Margin::same(5.)),
+                        )
+                        .show_inside(ui, |ui| {
+                            view_contact_request(ui, state, account, chat);
+                        });
+                }
+            }
+            if !state.selection.ids.is_empty() {
+                TopBottomPanel::bottom("selection")
+                    .frame(
+                        Frame::default()
+                            .fill(Color32::LIGHT_GRAY)
+                            .inner_margin(Margin::same(5.)),
+                    )
+                    .show_inside(ui, |ui| view_selection_bar(ui, state, &mut action));
+            } else if can_send {
+                // show the input-field for new messages
                 TopBottomPanel::bottom("input")
                     .frame(
                         Frame::default()
                             .fill(Color32::LIGHT_GRAY)
                             .inner_margin(Margin::same(2.)),
                     )
-                    .show_inside(ui, |ui| {
-                        ui.with_layout(
-                            egui::Layout::top_down_justified(egui::Align::Center),
-                            |ui| {
-                                let response =
-                                    ui.add(egui::TextEdit::singleline(&mut state.current_input));
-                                if response.lost_focus()
-                                    && ui.input(|i| i.key_pressed(egui::Key::Enter))
-                                {
-                                    let message = std::mem::take(&mut state.current_input);
-                                    if !message.is_empty() {
-                                        state.send_command(Command::SendTextMessage(message));
-                                    }
-
-                                    let text_edit_id = response.id;
-
-                                    // reselect focus
-                                    ui.ctx().memory_mut(|m| m.request_focus(text_edit_id));
-                                }
-                            },
-                        )
-                    });
+                    .show_inside(ui, |ui| render_composer(ui, state));
             }
 
             TopBottomPanel::top("chat")
@@ -91,177 +169,627 @@ pub fn render_main_panel(ctx: &Context, state: &mut AppState) {
                                     });
                             }
                         });
+                        render_chat_search(ui, state);
+                        let (scroll_to, anchor) = update_paging(ui.ctx(), state, account);
+                        let mut visible: Option<(usize, usize)> = None;
                         Frame::none().inner_margin(Margin::same(5.)).show(ui, |ui| {
-                            TableBuilder::new(ui)
+                            let mut table = TableBuilder::new(ui)
                                 .column(Column::remainder().at_least(100.0))
                                 .stick_to_bottom(true)
-                                .auto_shrink(false)
-                                .body(|mut body| {
-                                    info!("rendering body");
-                                    let shared_state = state.shared_state();
-                                    let msgs = &shared_state.message_list.messages;
-
-                                    let mut ui_cache = state.ui_cache.blocking_write();
-                                    let width = body.widths()[0];
-
-                                    let ctx = body.ui_mut().ctx().clone();
-                                    let mut cache_hits = 0;
-                                    let row_heights = msgs.iter().map(|msg| {
-                                        let id = msg.id().unwrap_or(u32::MAX);
-                                        if let Some(height) = ui_cache.get_message_height(id, width)
+                                .auto_shrink(false);
+                            if let Some(row) = scroll_to {
+                                table = table.scroll_to_row(row, Some(egui::Align::Center));
+                            } else if let Some(row) = anchor {
+                                table = table.scroll_to_row(row, Some(egui::Align::Min));
+                            }
+                            table.body(|mut body| {
+                                info!("rendering body");
+                                let shared_state = state.shared_state();
+                                let list = &shared_state.message_list;
+
+                                let mut ui_cache = state.ui_cache.blocking_write();
+                                let width = body.widths()[0];
+
+                                let ctx = body.ui_mut().ctx().clone();
+                                let mut cache_hits = 0;
+                                let account = shared_state
+                                    .shared_state
+                                    .selected_account
+                                    .unwrap_or_default();
+                                let row_heights =
+                                    list.items.iter().enumerate().map(|(index, item)| {
+                                        let msg = match list.get(index) {
+                                            Some(msg) => msg,
+                                            None => return PLACEHOLDER_HEIGHT,
+                                        };
+                                        let chat = list.chat_id;
+                                        if let Some(height) =
+                                            ui_cache.get_message_height(account, chat, item, width)
                                         {
                                             cache_hits += 1;
                                             height
                                         } else {
-                                            let height = calc_height(
+                                            let (height, known) = calc_height(
                                                 state,
                                                 &shared_state.shared_state,
                                                 &ctx,
                                                 width,
                                                 msg,
                                             );
-                                            ui_cache.set_message_height(id, width, height);
+                                            if known {
+                                                ui_cache.set_message_height(
+                                                    account, chat, item, width, height,
+                                                );
+                                            }
                                             height
                                         }
                                     });
 
-                                    body.heterogeneous_rows(row_heights, |mut row| {
-                                        let msg = msgs[row.index()].clone();
-                                        row.col(|ui| {
-                                            ui.add(ChatMessageWidget {
-                                                state: state.clone(),
-                                                msg,
+                                let selection = &state.selection.ids;
+                                body.heterogeneous_rows(row_heights, |mut row| {
+                                    let index = row.index();
+                                    visible = Some(match visible {
+                                        Some((first, last)) => (first.min(index), last.max(index)),
+                                        None => (index, index),
+                                    });
+                                    let msg = match list.get(index) {
+                                        Some(msg) => msg,
+                                        None => {
+                                            row.col(|ui| {
+                                                ui.weak("Loading…");
                                             });
+                                            return;
+                                        }
+                                    };
+                                    let selected =
+                                        msg.id().map_or(false, |id| selection.contains(&id));
+                                    row.col(|ui| {
+                                        ui.add(ChatMessageWidget {
+                                            state,
+                                            msg,
+                                            selecting: !selection.is_empty(),
+                                            selected,
+                                            action: &mut action,
                                         });
                                     });
-                                    info!(
-                                        "inserted {} rows ({} cache hits)",
-                                        msgs.len(),
-                                        cache_hits
-                                    );
                                 });
+                                info!(
+                                    "inserted {} rows ({} cache hits)",
+                                    list.items.len(),
+                                    cache_hits
+                                );
+                            });
                         });
+                        state.paging.visible = visible;
+                        if let Some(range) = visible.and_then(|visible| {
+                            page_to_load(&state.shared_state().message_list, visible)
+                        }) {
+                            request_messages(state, account, range);
+                        }
                     });
                 });
         });
+
+    match action {
+        Some(MessageAction::Reply(msg)) => {
+            state.composer.quote = Some(msg);
+            focus_composer(ctx);
+        }
+        Some(MessageAction::Edit(msg)) => {
+            edit_message(state, &msg);
+            focus_composer(ctx);
+        }
+        Some(MessageAction::ShowMessage(id)) => {
+            if let Some((account, chat)) = selected_chat {
+                state.scroll_to_message = Some((account, chat, id));
+                ctx.request_repaint();
+            }
+        }
+        Some(MessageAction::React(msg, reaction)) => {
+            if let Some(account) = state.shared_state().shared_state.selected_account {
+                state.send_command(Command::SendReaction(account, msg, reaction));
+            }
+        }
+        Some(MessageAction::Select(id)) => {
+            let ids = &mut state.selection.ids;
+            if ids.contains(&id) {
+                ids.remove(&id);
+            } else {
+                ids.insert(id);
+            }
+        }
+        Some(MessageAction::Delete(ids, for_all)) => {
+            if let Some((account, _)) = state.selection.chat {
+                state.selection.confirm_delete = Some((account, ids, for_all));
+            }
+        }
+        Some(MessageAction::Forward(ids)) => {
+            if let Some((account, _)) = state.selection.chat {
+                state.selection.ids.clear();
+                forward_messages(state, account, ids);
+            }
+        }
+        Some(MessageAction::Retry(id)) => {
+            if let Some((account, _)) = state.selection.chat {
+                state.send_command(Command::ResendMessages(account, vec![id]));
+            }
+        }
+        Some(MessageAction::Info(id)) => {
+            if let Some((account, _)) = state.selection.chat {
+                show_message_info(state, account, id);
+            }
+        }
+        None => {}
+    }
+
+    view_delete_dialog(ctx, state);
+}
+
+/// Finds the row to scroll to, if any, and the row to keep in place.
+///
+/// Loading or dropping messages above the shown ones changes the height of their rows,
+/// so the first shown row is kept in place unless the list sticks to the end.
+fn update_paging(
+    ctx: &Context,
+    state: &mut AppState,
+    account: Option<u32>,
+) -> (Option<usize>, Option<usize>) {
+    let (loaded, len, target) = {
+        let shared_state = state.shared_state();
+        let list = &shared_state.message_list;
+        // the chat might still be loading, keep the message until it shows up
+        let target = state
+            .scroll_to_message
+            .filter(|&(target_account, chat, _)| {
+                account == Some(target_account) && chat == list.chat_id
+            })
+            .and_then(|(_, _, id)| {
+                let row = list
+                    .items
+                    .iter()
+                    .position(|item| *item == ChatItem::Message(id))?;
+                Some((row, list.get(row).is_some()))
+            });
+        ((list.chat_id, list.range), list.items.len(), target)
+    };
+
+    let previous = state.paging.loaded.replace(loaded);
+    let mut anchor = None;
+    if previous != Some(loaded) {
+        state.paging.requested = None;
+        if let (Some((chat, range)), Some((first, last))) = (previous, state.paging.visible) {
+            if chat == loaded.0 && range.0 != loaded.1 .0 && last + 1 < len {
+                anchor = Some(first);
+            }
+        }
+    }
+    if let Some(requested) = state.paging.requested {
+        let elapsed = requested.elapsed();
+        if elapsed >= REQUEST_TIMEOUT {
+            state.paging.requested = None;
+        } else {
+            ctx.request_repaint_after(REQUEST_TIMEOUT - elapsed);
+        }
+    }
+
+    let scroll_to = match target {
+        Some((row, true)) => {
+            state.scroll_to_message = None;
+            Some(row)
+        }
+        Some((row, false)) => {
+            let start = row.saturating_sub(PAGE_SIZE / 2);
+            request_messages(state, account, (start, start + PAGE_SIZE));
+            None
+        }
+        None => None,
+    };
+    (scroll_to, anchor)
+}
+
+/// The page to load next for the shown rows, if they are close to or past the loaded ones.
+fn page_to_load(list: &MessageList, (first, last): (usize, usize)) -> Option<(usize, usize)> {
+    let (start, end) = list.range;
+    if last < start || first >= end {
+        let start = first.saturating_sub(PAGE_SIZE / 2);
+        Some((start, (last + PAGE_SIZE / 2).max(start + PAGE_SIZE)))
+    } else if start > 0 && first < start + PAGE_SIZE / 2 {
+        Some((start.saturating_sub(PAGE_SIZE), start))
+    } else if end < list.items.len() && last + PAGE_SIZE / 2 >= end {
+        Some((end, end + PAGE_SIZE))
+    } else {
+        None
+    }
+}
+
+/// Loads messages of the selected chat, unless a request is still pending.
+fn request_messages(state: &mut AppState, account: Option<u32>, range: (usize, usize)) {
+    let chat = state.shared_state().message_list.chat_id;
+    if let (Some(account), None) = (account, state.paging.requested) {
+        state.paging.requested = Some(Instant::now());
+        state.send_command(Command::LoadMessages {
+            account,
+            chat,
+            range,
+        });
+    }
+}
+
+fn view_selection_bar(ui: &mut Ui, state: &mut AppState, action: &mut Option<MessageAction>) {
+    ui.horizontal(|ui| {
+        let count = state.selection.ids.len();
+        ui.label(format!("{} selected", count));
+        if ui.button("↪ Forward…").clicked() {
+            *action = Some(MessageAction::Forward(
+                state.selection.ids.iter().copied().collect(),
+            ));
+        }
+        let ids: Vec<u32> = state.selection.ids.iter().copied().collect();
+        if ui.button("🗑 Delete for me…").clicked() {
+            *action = Some(MessageAction::Delete(ids.clone(), false));
+        }
+        let for_everyone = are_own_messages(&state.shared_state().message_list, &ids);
+        if ui
+            .add_enabled(for_everyone, egui::Button::new("🗑 Delete for everyone…"))
+            .on_disabled_hover_text("Only your own encrypted messages can be deleted for everyone")
+            .clicked()
+        {
+            *action = Some(MessageAction::Delete(ids, true));
+        }
+        if ui.button("Cancel").clicked() {
+            state.selection.ids.clear();
+        }
+    });
+}
+
+/// Whether all messages are loaded and deltachat allows deleting them for everyone.
+fn are_own_messages(list: &MessageList, ids: &[u32]) -> bool {
+    ids.iter().all(|id| {
+        list.messages.iter().any(|msg| match msg {
+            ChatMessage::Message(msg) => msg.id == *id && msg.is_own(),
+            ChatMessage::DayMarker(_) => false,
+        })
+    })
+}
+
+fn view_delete_dialog(ctx: &Context, state: &mut AppState) {
+    let (account, ids, for_all) = match state.selection.confirm_delete.as_ref() {
+        Some(confirm) => confirm.clone(),
+        None => return,
+    };
+
+    let mut delete = false;
+    let mut open = true;
+    egui::Window::new("Delete messages")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0., 0.])
+        .show(ctx, |ui| {
+            ui.set_width(300.);
+            let messages = if ids.len() == 1 {
+                "this message".to_string()
+            } else {
+                format!("these {} messages", ids.len())
+            };
+            if for_all {
+                ui.label(format!(
+                    "Delete {} for everyone? They are removed from this device, your server \
+                     and the devices of the other chat members.",
+                    messages
+                ));
+            } else {
+                ui.label(format!(
+                    "Delete {} for me? They are removed from this device and your server, \
+                     other chat members keep their copies.",
+                    messages
+                ));
+            }
+            ui.add_space(5.);
+            ui.horizontal(|ui| {
+                delete = ui.button("Delete").clicked();
+                if ui.button("Cancel").clicked() {
+                    open = false;
+                }
+            });
+        });
+
+    if delete {
+        state.send_command(Command::DeleteMessages(account, ids, for_all));
+        state.selection.ids.clear();
+    }
+    if delete || !open {
+        state.selection.confirm_delete = None;
+    }
+}
+
+/// Something the user did with a message, applied after the message list was rendered.
+enum MessageAction {
+    Reply(InnerChatMessage),
+    /// Loads the text of an own message into the composer.
+    Edit(InnerChatMessage),
+    ShowMessage(u32),
+    /// Sets our reaction to the message.
+    React(u32, String),
+    /// Adds the message to the selection or removes it.
+    Select(u32),
+    /// Asks to confirm deleting the messages, for everyone with `true`.
+    Delete(Vec<u32>, bool),
+    /// Asks for the chat to forward the messages to.
+    Forward(Vec<u32>),
+    /// Shows the details of the message.
+    Info(u32),
+    /// Sends a failed message again.
+    Retry(u32),
+}
+
+fn view_contact_request(ui: &mut Ui, state: &AppState, account: u32, chat: &ChatState) {
+    ui.vertical_centered(|ui| {
+        ui.label(
+            RichText::new(format!("{} would like to chat with you.", chat.name))
+                .family(egui::FontFamily::Name(FONT_REGULAR.into()))
+                .size(14.),
+        );
+        ui.horizontal(|ui| {
+            if ui.button("Accept").clicked() {
+                state.send_command(Command::AcceptContactRequest(account, chat.id));
+            }
+            if ui.button("Block").clicked() {
+                state.send_command(Command::BlockContact(account, chat.id));
+            }
+            if ui.button("Delete").clicked() {
+                state.send_command(Command::DeleteChat(account, chat.id));
+            }
+        });
+    });
 }
 
+/// Height of the row of a message, from the same layout the message is shown with.
+///
+/// Also returns whether the height is final, it isn't for images of unknown size until
+/// they are loaded.
 fn calc_height(
     state: &AppState,
     shared_state: &SharedState,
     ctx: &Context,
     width: f32,
     msg: &ChatMessage,
-) -> f32 {
-    // TODO: use font sizes
-    match msg {
-        ChatMessage::Message(msg) => {
-            if msg.is_info {
-                // single row
-                18.
-            } else if msg.is_first {
-                // avatar
-                20. + calc_line_height(state, shared_state, ctx, width, 45., msg).max(25.)
-            } else {
-                calc_line_height(state, shared_state, ctx, width, 0., msg)
-            }
+) -> (f32, bool) {
+    let style = ctx.style();
+    let spacing = &style.spacing;
+    ctx.fonts(|fonts| match msg {
+        ChatMessage::Message(msg) if msg.is_info => {
+            let font_id = font(14., FONT_REGULAR);
+            let galley = fonts.layout(msg.text.clone(), font_id, TEXT_COLOR, width);
+            (galley.size().y, true)
         }
-        ChatMessage::DayMarker(_) => {
-            // single row
-            18.
+        ChatMessage::Message(msg) if msg.is_first => {
+            let header = fonts
+                .row_height(&font(12., FONT_SEMI_BOLD))
+                .max(spacing.interact_size.y);
+            let inner_width = width - AVATAR_SIZE - spacing.item_spacing.x;
+            let (inner, known) =
+                calc_inner_height(state, shared_state, fonts, &style, inner_width, msg);
+            let content = header + spacing.item_spacing.y + inner;
+            (10. + content.max(AVATAR_SIZE), known)
         }
-    }
+        ChatMessage::Message(msg) => {
+            let inner_width = width - TIME_WIDTH - spacing.item_spacing.x;
+            calc_inner_height(state, shared_state, fonts, &style, inner_width, msg)
+        }
+        ChatMessage::DayMarker(_) => (fonts.row_height(&TextStyle::Body.resolve(&style)), true),
+    })
 }
 
-fn calc_line_height(
+/// Height of the content of a message, as laid out by [`view_inner_message`].
+fn calc_inner_height(
     state: &AppState,
     shared_state: &SharedState,
-    ctx: &Context,
+    fonts: &Fonts,
+    style: &Style,
     width: f32,
-    left_padding: f32,
     msg: &InnerChatMessage,
-) -> f32 {
-    // TODO: Load image and calculate size
-    let image_size = if msg.viewtype == Viewtype::Image || msg.viewtype == Viewtype::Gif {
-        if let Some(image) = msg
-            .file
-            .clone()
-            .and_then(|path| load_image(state, shared_state, ctx, msg.id, path))
-        {
-            let max_width = width - 10.;
-            let [_, height] = calc_image_size(&image, max_width);
-            height
-        } else {
-            200.
+) -> (f32, bool) {
+    let spacing = &style.spacing;
+    let mut known = true;
+    let mut heights = Vec::new();
+    if msg.is_forwarded {
+        heights.push(fonts.row_height(&font(12., FONT_LIGHT)));
+    }
+    if let Some(quote) = msg.quote.as_ref() {
+        heights.push(quote_height(fonts, spacing, &quote.text, width));
+    }
+    match msg.viewtype {
+        Viewtype::Image | Viewtype::Gif => {
+            if msg.file.is_some() {
+                let texture = cached_image(state, shared_state, msg.id);
+                match calc_image_size(msg, texture.as_ref(), width - 10.) {
+                    Some(size) => heights.push(size.y),
+                    None => known = false,
+                }
+            }
         }
-    } else {
-        0.
-    };
+        Viewtype::Unknown | Viewtype::Text => {}
+        _ => {
+            let content = unsupported_text(msg);
+            heights.push(text_height(fonts, &content, font(14., FONT_REGULAR), width));
+        }
+    }
+    if !msg.text.is_empty() {
+        heights.push(text_height(
+            fonts,
+            &msg.text,
+            font(14., FONT_REGULAR),
+            width,
+        ));
+    }
+    if msg.is_edited {
+        heights.push(fonts.row_height(&font(12., FONT_LIGHT)));
+    }
+    if !msg.reactions.is_empty() {
+        let chip =
+            fonts.row_height(&TextStyle::Button.resolve(style)) + 2. * spacing.button_padding.y;
+        heights.push(chip.max(spacing.interact_size.y));
+    }
 
-    let top_margin = 10.;
-    let font_size = 14.;
+    let gaps = heights.len().saturating_sub(1) as f32 * spacing.item_spacing.y;
+    let height = heights.iter().sum::<f32>() + gaps;
+    (height.max(spacing.interact_size.y), known)
+}
 
-    let total_text_len = msg.text.len() + msg.quote.as_ref().map(|q| q.text.len()).unwrap_or(0);
+/// Height of the row [`view_quote`] shows in the given width.
+fn quote_height(fonts: &Fonts, spacing: &Spacing, text: &str, width: f32) -> f32 {
+    let height = text_height(fonts, text, font(14., FONT_LIGHT), width - QUOTE_INDENT);
+    // horizontal layouts are at least as high as a button
+    height.max(spacing.interact_size.y)
+}
 
-    let text_height = if total_text_len > 0 {
-        // TODO: less naive
-        let num_chars = total_text_len as f32;
-        let num_chars_per_line = (width - left_padding) / font_size;
-        let num_lines = num_chars / num_chars_per_line;
-        num_lines * (font_size + 2.)
-    } else {
-        0.
-    };
-    top_margin + text_height + image_size
+/// Height of [`selectable_text`] showing `text` in the given width.
+fn text_height(fonts: &Fonts, text: &str, font_id: FontId, width: f32) -> f32 {
+    let row_height = fonts.row_height(&font_id);
+    let galley = layout_text(
+        fonts,
+        text,
+        font_id,
+        TEXT_COLOR,
+        "",
+        width - TEXT_MARGIN.sum().x,
+    );
+    galley.size().y.max(row_height) + TEXT_MARGIN.sum().y
 }
 
-struct ChatMessageWidget {
-    state: AppState,
-    msg: ChatMessage,
+fn font(size: f32, name: &str) -> FontId {
+    FontId::new(size, egui::FontFamily::Name(name.into()))
 }
 
-impl Widget for ChatMessageWidget {
+struct ChatMessageWidget<'a> {
+    state: &'a AppState,
+    msg: &'a ChatMessage,
+    /// Messages are being selected, clicks select instead of acting.
+    selecting: bool,
+    selected: bool,
+    action: &'a mut Option<MessageAction>,
+}
+
+impl<'a> Widget for ChatMessageWidget<'a> {
     fn ui(self, ui: &mut Ui) -> Response {
-        ui.scope(|ui| match &self.msg {
-            ChatMessage::Message(msg) => {
-                if msg.is_info {
-                    view_info_message(ui, &self.state, msg);
-                } else if msg.is_first {
-                    view_avatar_message(ui, &self.state, msg);
-                } else {
-                    view_simple_message(ui, &self.state, msg);
+        let action = self.action;
+        let mut targets = Vec::new();
+        if self.selected {
+            ui.painter()
+                .rect_filled(ui.max_rect(), 4., ACCENT_COLOR.linear_multiply(0.2));
+        }
+        let response = ui
+            .scope(|ui| match self.msg {
+                ChatMessage::Message(msg) => {
+                    if msg.is_info {
+                        view_info_message(ui, self.state, msg);
+                    } else if msg.is_first {
+                        view_avatar_message(ui, self.state, msg, &mut targets);
+                    } else {
+                        view_simple_message(ui, self.state, msg, &mut targets);
+                    }
                 }
-            }
-            ChatMessage::DayMarker(time) => {
-                // FIXME: make gray backround only as big as needed (not full width)
-                ui.vertical_centered(|ui| {
-                    Frame::none()
-                        .fill(Color32::from_gray(250))
-                        .rounding(4.)
-                        .show(ui, |ui| {
-                            ui.label(
-                                time.with_timezone(&chrono::Local)
-                                    .format("%d-%m-%Y")
-                                    .to_string(),
-                            );
-                        });
+                ChatMessage::DayMarker(time) => {
+                    // FIXME: make gray backround only as big as needed (not full width)
+                    ui.vertical_centered(|ui| {
+                        Frame::none()
+                            .fill(Color32::from_gray(250))
+                            .rounding(4.)
+                            .show(ui, |ui| {
+                                ui.label(
+                                    time.with_timezone(&chrono::Local)
+                                        .format("%d-%m-%Y")
+                                        .to_string(),
+                                );
+                            });
+                    });
+                }
+            })
+            .response;
+
+        match self.msg {
+            ChatMessage::Message(msg) if !msg.is_info => {
+                let response = response.interact(Sense::click());
+                if self.selecting {
+                    if response.clicked() {
+                        *action = Some(MessageAction::Select(msg.id));
+                    }
+                    return response;
+                }
+                response.context_menu(|ui| {
+                    if msg.state == MessageState::OutFailed && ui.button("⟳ Retry").clicked() {
+                        *action = Some(MessageAction::Retry(msg.id));
+                        ui.close_menu();
+                    }
+                    if ui.button("↩ Reply").clicked() {
+                        *action = Some(MessageAction::Reply(msg.clone()));
+                        ui.close_menu();
+                    }
+                    let can_send = self
+                        .state
+                        .shared_state()
+                        .shared_state
+                        .selected_chat
+                        .as_ref()
+                        .map_or(false, |chat| chat.can_send);
+                    if can_send
+                        && msg.is_own()
+                        && msg.viewtype == Viewtype::Text
+                        && ui.button("✏ Edit").clicked()
+                    {
+                        *action = Some(MessageAction::Edit(msg.clone()));
+                        ui.close_menu();
+                    }
+                    if ui.button("↪ Forward…").clicked() {
+                        *action = Some(MessageAction::Forward(vec![msg.id]));
+                        ui.close_menu();
+                    }
+                    if ui.button("☑ Select").clicked() {
+                        *action = Some(MessageAction::Select(msg.id));
+                        ui.close_menu();
+                    }
+                    if ui.button("ℹ Info").clicked() {
+                        *action = Some(MessageAction::Info(msg.id));
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button("🗑 Delete for me…").clicked() {
+                        *action = Some(MessageAction::Delete(vec![msg.id], false));
+                        ui.close_menu();
+                    }
+                    if msg.is_own() && ui.button("🗑 Delete for everyone…").clicked() {
+                        *action = Some(MessageAction::Delete(vec![msg.id], true));
+                        ui.close_menu();
+                    }
                 });
+
+                // registered after the whole message, so these get the clicks
+                for (i, (rect, target)) in targets.into_iter().enumerate() {
+                    let target_response = ui.interact(rect, response.id.with(i), Sense::click());
+                    if target_response
+                        .on_hover_cursor(egui::CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        *action = Some(target);
+                    }
+                }
+
+                view_reaction_picker(ui, msg, action);
+                response
             }
-        })
-        .response
+            _ => response,
+        }
     }
 }
 
 /// Renders an info message.
 fn view_info_message(ui: &mut Ui, _state: &AppState, msg: &InnerChatMessage) -> Response {
     ui.vertical_centered(|ui| {
-        let text_color = Color32::from_rgb(41, 51, 63);
-
         if !msg.text.is_empty() {
             ui.label(
                 RichText::new(msg.text.clone())
                     .size(14.)
-                    .color(text_color)
+                    .color(TEXT_COLOR)
                     .family(egui::FontFamily::Name(FONT_REGULAR.into())),
             );
         } else {
@@ -272,11 +800,15 @@ fn view_info_message(ui: &mut Ui, _state: &AppState, msg: &InnerChatMessage) ->
 }
 
 /// Renders a message with avatar.
-fn view_avatar_message(ui: &mut Ui, state: &AppState, msg: &InnerChatMessage) -> Response {
+fn view_avatar_message(
+    ui: &mut Ui,
+    state: &AppState,
+    msg: &InnerChatMessage,
+    targets: &mut Vec<(Rect, MessageAction)>,
+) -> Response {
     ui.add_space(10.);
 
     ui.horizontal(|ui| {
-        let text_color = Color32::from_rgb(41, 51, 63);
         let shared_state = state.shared_state();
         let account_id = shared_state
             .shared_state
@@ -296,7 +828,7 @@ fn view_avatar_message(ui: &mut Ui, state: &AppState, msg: &InnerChatMessage) ->
         ui.add(
             Avatar::new(
                 msg.from_first_name.to_string(),
-                Vec2::splat(40.),
+                Vec2::splat(AVATAR_SIZE),
                 image::color_from_u32(msg.from_color),
             )
             .rounding(Rounding::same(5.))
@@ -310,7 +842,7 @@ fn view_avatar_message(ui: &mut Ui, state: &AppState, msg: &InnerChatMessage) ->
                     RichText::new(&msg.from_first_name)
                         .family(egui::FontFamily::Name(FONT_SEMI_BOLD.into()))
                         .size(12.)
-                        .color(text_color),
+                        .color(TEXT_COLOR),
                 );
                 ui.label(
                     RichText::new(
@@ -320,23 +852,86 @@ fn view_avatar_message(ui: &mut Ui, state: &AppState, msg: &InnerChatMessage) ->
                     )
                     .family(egui::FontFamily::Name(FONT_LIGHT.into()))
                     .size(12.)
-                    .color(text_color),
+                    .color(TEXT_COLOR),
                 );
+                view_message_state(ui, msg, targets);
             });
-            view_inner_message(ui, state, &shared_state.shared_state, msg);
+            view_inner_message(ui, state, &shared_state.shared_state, msg, targets);
         });
     })
     .response
 }
 
 /// Renders a message without avatar, just the content
-fn view_simple_message(ui: &mut Ui, state: &AppState, msg: &InnerChatMessage) -> Response {
+fn view_simple_message(
+    ui: &mut Ui,
+    state: &AppState,
+    msg: &InnerChatMessage,
+    targets: &mut Vec<(Rect, MessageAction)>,
+) -> Response {
+    // time and state are only shown for the hovered message, decided from the whole
+    // row as the new horizontal layout doesn't cover anything yet, failed messages
+    // always show how to retry
+    let hovered = ui.rect_contains_pointer(ui.max_rect());
     ui.horizontal(|ui| {
-        ui.add_space(48.);
+        if hovered || msg.state == MessageState::OutFailed {
+            ui.allocate_ui(Vec2::new(TIME_WIDTH, 14.), |ui| {
+                ui.set_width(TIME_WIDTH);
+                ui.spacing_mut().item_spacing.x = 2.;
+                ui.label(
+                    RichText::new(
+                        msg.timestamp
+                            .map(|t| t.with_timezone(&chrono::Local).format("%H:%M").to_string())
+                            .unwrap_or_default(),
+                    )
+                    .family(egui::FontFamily::Name(FONT_LIGHT.into()))
+                    .size(10.)
+                    .color(Color32::DARK_GRAY),
+                );
+                view_message_state(ui, msg, targets);
+            });
+        } else {
+            // the same width as the time, so the text doesn't wrap differently on hover
+            ui.add_space(TIME_WIDTH + ui.spacing().item_spacing.x);
+        }
         let shared_state = state.shared_state();
-        view_inner_message(ui, state, &shared_state.shared_state, msg);
+        view_inner_message(ui, state, &shared_state.shared_state, msg, targets);
+    })
+    .response
+}
+
+/// Shows whether an outgoing message was sent, a failed one can be clicked to retry.
+fn view_message_state(
+    ui: &mut Ui,
+    msg: &InnerChatMessage,
+    targets: &mut Vec<(Rect, MessageAction)>,
+) {
+    let (icon, color, tooltip) = match msg.state {
+        MessageState::OutPreparing | MessageState::OutPending => ("🕓", Color32::GRAY, "Sending"),
+        MessageState::OutDelivered => ("✔", Color32::GRAY, "Delivered"),
+        MessageState::OutMdnRcvd => ("✔✔", *ACCENT_COLOR, "Read"),
+        MessageState::OutFailed => ("⚠", Color32::RED, "Failed to send, click to retry"),
+        _ => return,
+    };
+    let response = ui
+        .label(RichText::new(icon).size(10.).color(color))
+        .on_hover_text(tooltip);
+    if msg.state == MessageState::OutFailed {
+        targets.push((response.rect, MessageAction::Retry(msg.id)));
+    }
+}
+
+/// Shows the text of a quoted message, returns the area clicking shows the message.
+fn view_quote(ui: &mut Ui, text: &str, highlight: &str) -> Rect {
+    // TODO: render other types than text
+    ui.horizontal(|ui| {
+        ui.add_space(QUOTE_INDENT);
+        ui.horizontal_wrapped(|ui| {
+            selectable_text(ui, text, 14., FONT_LIGHT, TEXT_COLOR, highlight);
+        });
     })
     .response
+    .rect
 }
 
 fn view_inner_message(
@@ -344,38 +939,43 @@ fn view_inner_message(
     state: &AppState,
     shared_state: &SharedState,
     msg: &InnerChatMessage,
+    targets: &mut Vec<(Rect, MessageAction)>,
 ) {
+    let highlight = state.chat_search.highlight();
     ui.horizontal_wrapped(|ui| {
-        let text_color = Color32::from_rgb(41, 51, 63);
-        ui.visuals_mut().override_text_color = Some(text_color);
+        ui.visuals_mut().override_text_color = Some(TEXT_COLOR);
 
         // TODO: render other message types
 
         ui.vertical(|ui| {
-            if let Some(text) = msg.quote.as_ref().map(|q| q.text.clone()) {
-                // TODO: render other types than text
-
-                ui.horizontal(|ui| {
-                    ui.add_space(10.);
-                    ui.horizontal_wrapped(|ui| {
-                        ui.add(selectable_text(
-                            &mut text.as_str(),
-                            14.,
-                            FONT_LIGHT,
-                            text_color,
-                        ));
-                    });
-                });
+            if msg.is_forwarded {
+                ui.label(
+                    RichText::new("↪ Forwarded")
+                        .size(12.)
+                        .family(egui::FontFamily::Name(FONT_LIGHT.into()))
+                        .color(Color32::DARK_GRAY),
+                );
+            }
+            if let Some(quote) = msg.quote.as_ref() {
+                let quote_rect = view_quote(ui, &quote.text, highlight);
+                targets.push((quote_rect, MessageAction::ShowMessage(quote.id)));
             }
 
             match msg.viewtype {
                 Viewtype::Image | Viewtype::Gif => {
                     if let Some(path) = msg.file.clone() {
-                        if let Some(image) = load_image(state, shared_state, ui.ctx(), msg.id, path)
-                        {
-                            let max_width = ui.available_width() - 10.;
-                            let size = calc_image_size(&image, max_width);
-                            ui.image(SizedTexture::new(image.id(), size));
+                        let texture = load_image(state, shared_state, ui.ctx(), msg.id, path);
+                        let max_width = ui.available_width() - 10.;
+                        let size = calc_image_size(msg, texture.as_ref(), max_width);
+                        match (texture, size) {
+                            (Some(texture), Some(size)) => {
+                                ui.image(SizedTexture::new(texture.id(), size));
+                            }
+                            (None, Some(size)) => {
+                                // keep the space of the image while it is loading
+                                ui.allocate_space(size);
+                            }
+                            _ => {}
                         }
                     }
                 }
@@ -385,14 +985,10 @@ fn view_inner_message(
                 | Viewtype::VideochatInvitation
                 | Viewtype::Voice
                 | Viewtype::Webxdc
+                | Viewtype::Vcard
                 | Viewtype::File => {
-                    let content = format!("{:?} not yet supported", msg.viewtype);
-                    ui.add(selectable_text(
-                        &mut content.as_str(),
-                        14.,
-                        FONT_REGULAR,
-                        text_color,
-                    ));
+                    let content = unsupported_text(msg);
+                    selectable_text(ui, &content, 14., FONT_REGULAR, TEXT_COLOR, "");
                 }
                 Viewtype::Unknown => {}
                 Viewtype::Text => { /* Text rendering is done below */ }
@@ -400,29 +996,122 @@ fn view_inner_message(
 
             // render additional in all cases text
             if !msg.text.is_empty() {
-                ui.add(selectable_text(
-                    &mut msg.text.as_str(),
-                    14.,
-                    FONT_REGULAR,
-                    text_color,
-                ));
+                selectable_text(ui, &msg.text, 14., FONT_REGULAR, TEXT_COLOR, highlight);
+            }
+            if msg.is_edited {
+                ui.label(
+                    RichText::new("edited")
+                        .size(12.)
+                        .family(egui::FontFamily::Name(FONT_LIGHT.into()))
+                        .color(Color32::DARK_GRAY),
+                );
+            }
+
+            if !msg.reactions.is_empty() {
+                ui.horizontal(|ui| {
+                    for reaction in &msg.reactions {
+                        let chip = ui.selectable_label(
+                            reaction.is_self,
+                            format!("{} {}", display_emoji(&reaction.emoji), reaction.count),
+                        );
+                        let chip = if reaction.is_self {
+                            chip.on_hover_text("Remove your reaction")
+                        } else {
+                            chip
+                        };
+                        let reaction = toggle_reaction(msg, &reaction.emoji);
+                        targets.push((chip.rect, MessageAction::React(msg.id, reaction)));
+                    }
+                });
+            }
+        });
+    });
+}
+
+/// Emojis offered by the reaction picker.
+const REACTIONS: [&str; 6] = ["👍", "👎", "❤️", "😆", "😯", "😢"];
+
+/// Shows a button to react to the hovered message.
+fn view_reaction_picker(ui: &mut Ui, msg: &InnerChatMessage, action: &mut Option<MessageAction>) {
+    let popup_id = ui.make_persistent_id(("reaction-picker", msg.id));
+    let open = ui.memory(|m| m.is_popup_open(popup_id));
+    if !open && !ui.rect_contains_pointer(ui.max_rect()) {
+        return;
+    }
+
+    let rect = Rect::from_min_size(
+        ui.max_rect().right_top() + Vec2::new(-30., 0.),
+        Vec2::splat(24.),
+    );
+    let button = ui
+        .put(rect, egui::Button::new("☺").small())
+        .on_hover_text("React");
+    if button.clicked() {
+        ui.memory_mut(|m| m.toggle_popup(popup_id));
+    }
+    egui::popup_below_widget(ui, popup_id, &button, |ui| {
+        ui.horizontal(|ui| {
+            for emoji in REACTIONS {
+                let selected = msg.reactions.iter().any(|r| r.is_self && r.emoji == emoji);
+                let button =
+                    ui.selectable_label(selected, RichText::new(display_emoji(emoji)).size(18.));
+                if button.clicked() {
+                    *action = Some(MessageAction::React(msg.id, toggle_reaction(msg, emoji)));
+                }
             }
         });
     });
 }
 
-fn calc_image_size(image: &TextureHandle, max_width: f32) -> [f32; 2] {
-    let image_size = image.size();
-    if max_width < image_size[0] as f32 {
+/// Our new reaction when `emoji` is picked, picking our current reaction removes it.
+fn toggle_reaction(msg: &InnerChatMessage, emoji: &str) -> String {
+    let is_current = msg.reactions.iter().any(|r| r.is_self && r.emoji == emoji);
+    if is_current {
+        String::new()
+    } else {
+        emoji.to_string()
+    }
+}
+
+/// The emoji without variation selectors, which the fonts don't have glyphs for.
+fn display_emoji(emoji: &str) -> String {
+    emoji.replace('\u{fe0f}', "")
+}
+
+fn unsupported_text(msg: &InnerChatMessage) -> String {
+    format!("{:?} not yet supported", msg.viewtype)
+}
+
+/// Size of the image of a message, from the file until the texture is loaded.
+///
+/// Returns `None` while neither knows the size.
+fn calc_image_size(
+    msg: &InnerChatMessage,
+    texture: Option<&TextureHandle>,
+    max_width: f32,
+) -> Option<Vec2> {
+    let size = match texture {
+        Some(texture) => Vec2::new(texture.size()[0] as f32, texture.size()[1] as f32),
+        None if msg.file_width > 0 && msg.file_height > 0 => {
+            Vec2::new(msg.file_width as f32, msg.file_height as f32)
+        }
+        None => return None,
+    };
+    if max_width < size.x {
         // too wide, scale down
-        let factor = image_size[0] as f32 / max_width;
-        [max_width, image_size[1] as f32 / factor]
+        Some(Vec2::new(max_width, size.y * max_width / size.x))
     } else {
         // wide enough
-        [image_size[0] as f32, image_size[1] as f32]
+        Some(size)
     }
 }
 
+fn image_name(shared_state: &SharedState, msg_id: u32) -> String {
+    let account_id = shared_state.selected_account.unwrap_or_default();
+    let chat_id = shared_state.selected_chat_id.unwrap_or_default();
+    format!("image-{}-{}-{}", account_id, chat_id, msg_id)
+}
+
 fn load_image(
     state: &AppState,
     shared_state: &SharedState,
@@ -430,23 +1119,134 @@ fn load_image(
     msg_id: u32,
     path: PathBuf,
 ) -> Option<TextureHandle> {
-    let account_id = shared_state.selected_account.unwrap_or_default();
-    let chat_id = shared_state.selected_chat_id.unwrap_or_default();
-    let id = format!("image-{}-{}-{}", account_id, chat_id, msg_id);
-
+    let id = image_name(shared_state, msg_id);
     state.get_or_load_image(ctx, id, move |_name| image::load_image_from_path(&path))
 }
 
-fn selectable_text<'a>(
-    content: &'a mut &'a str,
+/// The texture of the image of a message, without loading it.
+fn cached_image(
+    state: &AppState,
+    shared_state: &SharedState,
+    msg_id: u32,
+) -> Option<TextureHandle> {
+    let id = image_name(shared_state, msg_id);
+    state.image_cache.blocking_read().peek(&id).cloned()
+}
+
+/// Adds text that can be selected, with the matches of `highlight` marked.
+fn selectable_text(
+    ui: &mut Ui,
+    text: &str,
     size: f32,
     font_name: &str,
     color: Color32,
-) -> TextEdit<'a> {
-    TextEdit::multiline(content)
-        .font(FontId::new(size, egui::FontFamily::Name(font_name.into())))
-        .text_color(color)
-        .desired_rows(1)
-        .desired_width(f32::INFINITY)
-        .frame(false)
+    highlight: &str,
+) -> Response {
+    let font_id = font(size, font_name);
+    let mut layouter = |ui: &Ui, text: &str, wrap_width: f32| {
+        ui.fonts(|fonts| layout_text(fonts, text, font_id.clone(), color, highlight, wrap_width))
+    };
+
+    let mut content = text;
+    ui.add(
+        TextEdit::multiline(&mut content)
+            .font(font_id.clone())
+            .text_color(color)
+            .desired_rows(1)
+            .desired_width(f32::INFINITY)
+            .frame(false)
+            .margin(TEXT_MARGIN)
+            .layouter(&mut layouter),
+    )
+}
+
+/// Lays out text as [`selectable_text`] shows it.
+fn layout_text(
+    fonts: &Fonts,
+    text: &str,
+    font_id: FontId,
+    color: Color32,
+    highlight: &str,
+    wrap_width: f32,
+) -> Arc<Galley> {
+    let format = TextFormat::simple(font_id, color);
+    let mut job = highlighted(text, &find_matches(text, highlight), format);
+    job.wrap.max_width = wrap_width;
+    fonts.layout_job(job)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(len: usize, range: (usize, usize)) -> MessageList {
+        MessageList {
+            chat_id: 10,
+            range,
+            items: (0..len as u32).map(ChatItem::Message).collect(),
+            messages: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_quote_height() {
+        let ctx = Context::default();
+        let mut fonts = egui::FontDefinitions::default();
+        for name in [FONT_LIGHT, FONT_REGULAR, FONT_SEMI_BOLD] {
+            fonts.families.insert(
+                egui::FontFamily::Name(name.into()),
+                vec!["Ubuntu-Light".into()],
+            );
+        }
+        ctx.set_fonts(fonts);
+
+        let quote = "a quote long enough to wrap into several rows ".repeat(4);
+        for width in [120., 300., 600.] {
+            let mut heights = (0., 0.);
+            // the fonts are only used from the second frame on
+            for _ in 0..2 {
+                let _ = ctx.run(Default::default(), |ctx| {
+                    CentralPanel::default().show(ctx, |ui| {
+                        ui.allocate_ui(Vec2::new(width, 1000.), |ui| {
+                            let rendered = view_quote(ui, &quote, "").height();
+                            let computed =
+                                ui.fonts(|fonts| quote_height(fonts, ui.spacing(), &quote, width));
+                            heights = (rendered, computed);
+                        });
+                    });
+                });
+            }
+            assert_eq!(heights.0, heights.1, "width {}", width);
+        }
+    }
+
+    #[test]
+    fn test_page_to_load() {
+        let half = PAGE_SIZE / 2;
+        let end_loaded = list(1000, (800, 1000));
+
+        // well within the loaded messages
+        assert_eq!(page_to_load(&end_loaded, (900, 920)), None);
+        // close to the start, the page before is loaded
+        assert_eq!(
+            page_to_load(&end_loaded, (800 + half - 1, 850)),
+            Some((800 - PAGE_SIZE, 800))
+        );
+        // the end of the chat is loaded, nothing comes after it
+        assert_eq!(page_to_load(&end_loaded, (980, 999)), None);
+        // far away, the shown rows are loaded with some context
+        assert_eq!(
+            page_to_load(&end_loaded, (100, 110)),
+            Some((100 - half, 110 + half))
+        );
+
+        // close to the end of a range that doesn't reach the end of the chat
+        let start_loaded = list(1000, (0, 200));
+        assert_eq!(
+            page_to_load(&start_loaded, (160, 200 - half)),
+            Some((200, 200 + PAGE_SIZE))
+        );
+        // nothing before the first message
+        assert_eq!(page_to_load(&start_loaded, (0, 20)), None);
+    }
 }
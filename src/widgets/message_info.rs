@@ -0,0 +1,105 @@
+use chrono::{DateTime, Utc};
+use egui::{Align2, Context, Grid, RichText, ScrollArea, TextStyle, Window};
+
+use crate::{
+    dc::types::MessageInfo,
+    state::{AppState, Command},
+};
+
+/// The message whose details are shown, if any.
+#[derive(Debug, Default, Clone)]
+pub struct MessageInfoDialog {
+    msg: Option<u32>,
+}
+
+/// Opens the info dialog for a message of `account`.
+pub fn show_message_info(state: &mut AppState, account: u32, msg: u32) {
+    state.message_info_dialog.msg = Some(msg);
+    state.send_command(Command::LoadMessageInfo(account, msg));
+}
+
+pub fn render_message_info(ctx: &Context, state: &mut AppState) {
+    let msg = match state.message_info_dialog.msg {
+        Some(msg) => msg,
+        None => return,
+    };
+    let info = state
+        .shared_state()
+        .message_info
+        .clone()
+        .filter(|info| info.id == msg);
+
+    let mut open = true;
+    Window::new("Message info")
+        .collapsible(false)
+        .default_width(400.)
+        .anchor(Align2::CENTER_CENTER, [0., 0.])
+        .open(&mut open)
+        .show(ctx, |ui| {
+            let info = match info {
+                Some(info) => info,
+                None => {
+                    ui.spinner();
+                    return;
+                }
+            };
+
+            Grid::new("message-info").num_columns(2).show(ui, |ui| {
+                ui.label("Sent");
+                ui.label(format_time(info.sent));
+                ui.end_row();
+
+                ui.label("Received");
+                ui.label(format_time(info.received));
+                ui.end_row();
+
+                ui.label("State");
+                ui.label(&info.state);
+                ui.end_row();
+
+                ui.label("Encryption");
+                ui.label(if info.is_encrypted {
+                    "🔒 End-to-end encrypted"
+                } else {
+                    "Not end-to-end encrypted"
+                });
+                ui.end_row();
+
+                if let Some(error) = &info.error {
+                    ui.label("Error");
+                    ui.label(RichText::new(error).color(ui.visuals().error_fg_color));
+                    ui.end_row();
+                }
+            });
+
+            ui.separator();
+            ScrollArea::vertical().max_height(300.).show(ui, |ui| {
+                ui.label(RichText::new(&info.report).text_style(TextStyle::Monospace));
+            });
+            ui.separator();
+            if ui.button("📋 Copy").clicked() {
+                ui.ctx().copy_text(copy_text(&info));
+            }
+        });
+
+    if !open {
+        state.message_info_dialog.msg = None;
+    }
+}
+
+fn format_time(time: Option<DateTime<Utc>>) -> String {
+    time.map(|time| {
+        time.with_timezone(&chrono::Local)
+            .format("%d-%m-%Y %H:%M:%S")
+            .to_string()
+    })
+    .unwrap_or_else(|| "-".to_string())
+}
+
+/// The report of deltachat, followed by an error if there is one.
+fn copy_text(info: &MessageInfo) -> String {
+    match &info.error {
+        Some(error) => format!("{}\nError: {}", info.report, error),
+        None => info.report.clone(),
+    }
+}
@@ -1,5 +1,12 @@
 pub mod accounts;
 pub mod avatar;
 pub mod chat_list;
+pub mod chat_search;
+pub mod composer;
+pub mod forward;
+pub mod imex;
 pub mod mainpanel;
+pub mod message_info;
+pub mod onboarding;
+pub mod search;
 pub mod sidebar;
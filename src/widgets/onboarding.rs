@@ -0,0 +1,334 @@
+use egui::{
+    Align, CentralPanel, CollapsingHeader, Color32, ComboBox, Context, Frame, Grid, Layout,
+    ProgressBar, RichText, TextEdit, Ui,
+};
+
+use super::imex::import_backup;
+use crate::{
+    app::{FONT_REGULAR, FONT_SEMI_BOLD},
+    dc::types::{CertificateChecks, Login, Security, ServerSettings},
+    state::{AppState, Command},
+    ACCENT_COLOR,
+};
+
+/// Input of the login screen, kept around between frames.
+#[derive(Debug, Default, Clone)]
+pub struct LoginForm {
+    /// Show the form, even if there are accounts already.
+    pub open: bool,
+    pub email: String,
+    pub password: String,
+    /// Advanced server settings, ports are kept as typed until submitted.
+    pub settings: ServerSettings,
+    pub imap_port: String,
+    pub smtp_port: String,
+    /// A login for `email` was started and we are waiting for its result.
+    pending: bool,
+}
+
+impl LoginForm {
+    pub fn is_valid(&self) -> bool {
+        let email = self.email.trim();
+        !self.password.is_empty()
+            && email
+                .split_once('@')
+                .map(|(local, domain)| !local.is_empty() && !domain.is_empty())
+                .unwrap_or(false)
+            && parse_port(&self.imap_port).is_ok()
+            && parse_port(&self.smtp_port).is_ok()
+    }
+
+    /// The server settings including the parsed ports.
+    pub fn server_settings(&self) -> ServerSettings {
+        ServerSettings {
+            imap_port: parse_port(&self.imap_port).ok().flatten(),
+            smtp_port: parse_port(&self.smtp_port).ok().flatten(),
+            ..self.settings.clone()
+        }
+    }
+}
+
+/// Parses a port field, an empty field means automatic.
+fn parse_port(port: &str) -> Result<Option<u16>, std::num::ParseIntError> {
+    let port = port.trim();
+    if port.is_empty() {
+        Ok(None)
+    } else {
+        port.parse().map(Some)
+    }
+}
+
+pub fn render_onboarding(ctx: &Context, state: &mut AppState) {
+    CentralPanel::default()
+        .frame(Frame::default().fill(Color32::WHITE).inner_margin(20.))
+        .show(ctx, |ui| {
+            ui.with_layout(Layout::top_down(Align::Center), |ui| {
+                ui.set_max_width(360.);
+                view_login_form(ui, state);
+            });
+        });
+}
+
+fn view_login_form(ui: &mut Ui, state: &mut AppState) {
+    let (login, step, has_accounts) = {
+        let shared_state = state.shared_state();
+        let login = shared_state.login.clone().filter(|login| {
+            state.login_form.pending && login.email == state.login_form.email.trim()
+        });
+        (
+            login.as_ref().map(|login| login.status.clone()),
+            login.map(|login| login.step),
+            !shared_state.shared_state.accounts.is_empty(),
+        )
+    };
+
+    if login == Some(Login::Success) {
+        // done, the backend selects the new account
+        state.login_form = LoginForm::default();
+        return;
+    }
+    let in_progress = matches!(login, Some(Login::Progress(_)));
+
+    ui.add_space(40.);
+    Frame::none()
+        .fill(*ACCENT_COLOR)
+        .rounding(5.)
+        .inner_margin(10.)
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width());
+            ui.label(
+                RichText::new("Welcome to Dreamer")
+                    .family(egui::FontFamily::Name(FONT_SEMI_BOLD.into()))
+                    .size(20.)
+                    .color(Color32::WHITE),
+            );
+            ui.label(
+                RichText::new("Log in with your email account to get started.")
+                    .family(egui::FontFamily::Name(FONT_REGULAR.into()))
+                    .size(14.)
+                    .color(Color32::LIGHT_GRAY),
+            );
+        });
+    ui.add_space(10.);
+
+    let mut command = None;
+    let form = &mut state.login_form;
+    ui.add_enabled_ui(!in_progress, |ui| {
+        ui.add(
+            TextEdit::singleline(&mut form.email)
+                .hint_text("Email address")
+                .desired_width(f32::INFINITY),
+        );
+        let password = ui.add(
+            TextEdit::singleline(&mut form.password)
+                .hint_text("Password")
+                .password(true)
+                .desired_width(f32::INFINITY),
+        );
+        let submitted = password.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+
+        CollapsingHeader::new("Advanced")
+            .default_open(false)
+            .show(ui, |ui| view_server_settings(ui, form));
+
+        ui.add_space(5.);
+        ui.horizontal(|ui| {
+            let login = ui.add_enabled(form.is_valid(), egui::Button::new("Log in"));
+            if (login.clicked() || submitted) && form.is_valid() {
+                form.open = true;
+                form.pending = true;
+                command = Some(Command::Login {
+                    email: form.email.trim().to_string(),
+                    password: form.password.clone(),
+                    settings: form.server_settings(),
+                });
+            }
+            if has_accounts && ui.button("Cancel").clicked() {
+                *form = LoginForm::default();
+            }
+        });
+    });
+    if let Some(command) = command {
+        state.send_command(command);
+    }
+
+    ui.add_space(10.);
+    ui.separator();
+    let import = ui.add_enabled(!in_progress, egui::Button::new("Import backup…"));
+    if import
+        .on_hover_text("Restore an account from a Delta Chat backup")
+        .clicked()
+    {
+        import_backup(state);
+    }
+
+    match login {
+        Some(Login::Progress(step)) => {
+            ui.add_space(10.);
+            ui.add(
+                ProgressBar::new(step as f32 / 1000.)
+                    .show_percentage()
+                    .animate(true),
+            );
+        }
+        Some(Login::Error(err)) => {
+            ui.add_space(10.);
+            if let Some(label) = failed_step_label(step) {
+                ui.label(label);
+            }
+            ui.label(RichText::new(err).color(Color32::DARK_RED));
+        }
+        Some(Login::Success) | Some(Login::Not) | None => {}
+    }
+}
+
+/// Where the setup stopped, from the last progress step in permille.
+fn failed_step_label(step: Option<usize>) -> Option<String> {
+    step.filter(|step| *step > 0)
+        .map(|step| format!("Setup failed at {}%:", step / 10))
+}
+
+fn view_server_settings(ui: &mut Ui, form: &mut LoginForm) {
+    let settings = &mut form.settings;
+
+    Grid::new("server-settings")
+        .num_columns(2)
+        .striped(false)
+        .show(ui, |ui| {
+            ui.label(RichText::new("Incoming (IMAP)").strong());
+            ui.end_row();
+
+            ui.label("Server");
+            ui.add(text(&mut settings.imap_host, "automatic", false));
+            ui.end_row();
+            ui.label("Port");
+            ui.add(text(&mut form.imap_port, "automatic", false));
+            ui.end_row();
+            ui.label("Security");
+            security_combo(ui, "imap-security", &mut settings.imap_security);
+            ui.end_row();
+            ui.label("Login name");
+            ui.add(text(&mut settings.imap_user, "email address", false));
+            ui.end_row();
+
+            ui.label(RichText::new("Outgoing (SMTP)").strong());
+            ui.end_row();
+
+            ui.label("Server");
+            ui.add(text(&mut settings.smtp_host, "automatic", false));
+            ui.end_row();
+            ui.label("Port");
+            ui.add(text(&mut form.smtp_port, "automatic", false));
+            ui.end_row();
+            ui.label("Security");
+            security_combo(ui, "smtp-security", &mut settings.smtp_security);
+            ui.end_row();
+            ui.label("Login name");
+            ui.add(text(&mut settings.smtp_user, "email address", false));
+            ui.end_row();
+            ui.label("Password");
+            ui.add(text(&mut settings.smtp_password, "IMAP password", true));
+            ui.end_row();
+
+            ui.label("Certificates");
+            ComboBox::from_id_source("certificate-checks")
+                .selected_text(certificate_checks_label(settings.certificate_checks))
+                .show_ui(ui, |ui| {
+                    for checks in [
+                        CertificateChecks::Automatic,
+                        CertificateChecks::Strict,
+                        CertificateChecks::AcceptInvalidCertificates,
+                    ] {
+                        ui.selectable_value(
+                            &mut settings.certificate_checks,
+                            checks,
+                            certificate_checks_label(checks),
+                        );
+                    }
+                });
+            ui.end_row();
+        });
+}
+
+fn text<'a>(value: &'a mut String, hint: &str, password: bool) -> TextEdit<'a> {
+    TextEdit::singleline(value)
+        .hint_text(hint)
+        .password(password)
+        .desired_width(f32::INFINITY)
+}
+
+fn security_combo(ui: &mut Ui, id: &str, security: &mut Security) {
+    ComboBox::from_id_source(id)
+        .selected_text(security_label(*security))
+        .show_ui(ui, |ui| {
+            for value in [
+                Security::Automatic,
+                Security::Ssl,
+                Security::Starttls,
+                Security::Plain,
+            ] {
+                ui.selectable_value(security, value, security_label(value));
+            }
+        });
+}
+
+fn security_label(security: Security) -> &'static str {
+    match security {
+        Security::Automatic => "Automatic",
+        Security::Ssl => "SSL/TLS",
+        Security::Starttls => "STARTTLS",
+        Security::Plain => "Off",
+    }
+}
+
+fn certificate_checks_label(checks: CertificateChecks) -> &'static str {
+    match checks {
+        CertificateChecks::Automatic => "Automatic",
+        CertificateChecks::Strict => "Strict",
+        CertificateChecks::AcceptInvalidCertificates => "Accept invalid certificates",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_login_form_is_valid() {
+        let form = |email: &str, password: &str| LoginForm {
+            email: email.into(),
+            password: password.into(),
+            ..Default::default()
+        };
+
+        assert!(form("alice@example.org", "secret").is_valid());
+        assert!(form(" alice@example.org ", "secret").is_valid());
+        assert!(!form("alice@example.org", "").is_valid());
+        assert!(!form("alice", "secret").is_valid());
+        assert!(!form("@example.org", "secret").is_valid());
+        assert!(!form("alice@", "secret").is_valid());
+
+        let mut with_port = form("alice@example.org", "secret");
+        with_port.imap_port = "993".into();
+        assert!(with_port.is_valid());
+        assert_eq!(with_port.server_settings().imap_port, Some(993));
+        assert_eq!(with_port.server_settings().smtp_port, None);
+        with_port.smtp_port = "smtp".into();
+        assert!(!with_port.is_valid());
+    }
+
+    #[test]
+    fn test_failed_step_label() {
+        assert_eq!(failed_step_label(None), None);
+        // failing before the first step has nothing to show
+        assert_eq!(failed_step_label(Some(0)), None);
+        assert_eq!(
+            failed_step_label(Some(450)).as_deref(),
+            Some("Setup failed at 45%:")
+        );
+        assert_eq!(
+            failed_step_label(Some(999)).as_deref(),
+            Some("Setup failed at 99%:")
+        );
+    }
+}
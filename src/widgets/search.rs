@@ -0,0 +1,303 @@
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use egui::{
+    text::LayoutJob, Color32, Context, CursorIcon, FontId, Frame, Id, Margin, RichText, ScrollArea,
+    Sense, TextEdit, TextFormat, TopBottomPanel, Ui,
+};
+
+use crate::{
+    app::{FONT_LIGHT, FONT_SEMI_BOLD},
+    dc::types::SearchResult,
+    state::{AppState, Command},
+};
+
+/// Pause in typing after which the search starts.
+pub const SEARCH_DELAY: Duration = Duration::from_millis(300);
+
+/// Characters shown before and after the match in a result.
+const SNIPPET_CONTEXT: usize = 40;
+
+pub const HIGHLIGHT_COLOR: Color32 = Color32::from_rgb(255, 236, 140);
+
+/// The search over all chats.
+#[derive(Debug, Default, Clone)]
+pub struct GlobalSearch {
+    query: String,
+    all_accounts: bool,
+    edited: Option<Instant>,
+}
+
+pub fn render_search_bar(ctx: &Context, state: &mut AppState) {
+    let mut chosen = None;
+    TopBottomPanel::top("search")
+        .frame(
+            Frame::default()
+                .fill(Color32::from_gray(250))
+                .inner_margin(Margin::same(5.)),
+        )
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let response = ui.add(
+                    TextEdit::singleline(&mut state.search.query)
+                        .id(Id::new("global-search"))
+                        .hint_text("🔍 Search messages")
+                        .desired_width(300.),
+                );
+                let popup_id = ui.make_persistent_id("global-search-results");
+                if response.changed() {
+                    state.search.edited = Some(Instant::now());
+                    ui.memory_mut(|mem| mem.open_popup(popup_id));
+                }
+                if ui
+                    .checkbox(&mut state.search.all_accounts, "All accounts")
+                    .changed()
+                {
+                    // search again right away, if the clock allows to go back that far
+                    let now = Instant::now();
+                    state.search.edited = Some(now.checked_sub(SEARCH_DELAY).unwrap_or(now));
+                    ui.memory_mut(|mem| mem.open_popup(popup_id));
+                }
+
+                if state.search.query.trim().is_empty() {
+                    return;
+                }
+                egui::popup::popup_below_widget(ui, popup_id, &response, |ui| {
+                    ui.set_width(450.);
+                    chosen = view_results(ui, state);
+                });
+            });
+        });
+
+    if let Some(edited) = state.search.edited {
+        let elapsed = edited.elapsed();
+        if elapsed >= SEARCH_DELAY {
+            state.search.edited = None;
+            let query = state.search.query.trim().to_string();
+            if !query.is_empty() {
+                state.send_command(Command::Search {
+                    query,
+                    all_accounts: state.search.all_accounts,
+                });
+            }
+        } else {
+            ctx.request_repaint_after(SEARCH_DELAY - elapsed);
+        }
+    }
+
+    if let Some(result) = chosen {
+        if state.shared_state().shared_state.selected_account != Some(result.account) {
+            state.send_command(Command::SelectAccount(result.account));
+        }
+        state.send_command(Command::SelectChat(result.account, result.chat_id));
+        state.scroll_to_message = Some((result.account, result.chat_id, result.msg_id));
+        ctx.memory_mut(|mem| mem.close_popup());
+    }
+}
+
+/// Lists the results of the current query, returns the one that was clicked.
+fn view_results(ui: &mut Ui, state: &AppState) -> Option<SearchResult> {
+    let query = state.search.query.trim();
+    let shared_state = state.shared_state();
+    let search = shared_state
+        .search
+        .as_ref()
+        .filter(|search| search.query == query && search.all_accounts == state.search.all_accounts);
+    let results = match search {
+        Some(search) => &search.results,
+        None => {
+            ui.spinner();
+            return None;
+        }
+    };
+    if results.is_empty() {
+        ui.label("No messages found");
+        return None;
+    }
+
+    let mut chosen = None;
+    ScrollArea::vertical().max_height(400.).show(ui, |ui| {
+        for result in results {
+            let account_name = if state.search.all_accounts {
+                shared_state
+                    .shared_state
+                    .accounts
+                    .get(&result.account)
+                    .map(|account| {
+                        account
+                            .display_name
+                            .clone()
+                            .unwrap_or_else(|| account.email.clone())
+                    })
+            } else {
+                None
+            };
+            let response = view_result(ui, result, account_name, query);
+            if response.clicked() {
+                chosen = Some(result.clone());
+            }
+            ui.separator();
+        }
+    });
+    chosen
+}
+
+fn view_result(
+    ui: &mut Ui,
+    result: &SearchResult,
+    account_name: Option<String>,
+    query: &str,
+) -> egui::Response {
+    let response = Frame::none()
+        .inner_margin(Margin::same(4.))
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width());
+            ui.horizontal(|ui| {
+                ui.label(
+                    RichText::new(&result.chat_name)
+                        .family(egui::FontFamily::Name(FONT_SEMI_BOLD.into()))
+                        .size(13.),
+                );
+                let mut details = vec![result.from_name.clone()];
+                if let Some(time) = result.timestamp {
+                    details.push(
+                        time.with_timezone(&chrono::Local)
+                            .format("%d-%m-%Y %H:%M")
+                            .to_string(),
+                    );
+                }
+                details.extend(account_name);
+                ui.label(
+                    RichText::new(details.join(" · "))
+                        .family(egui::FontFamily::Name(FONT_LIGHT.into()))
+                        .size(12.)
+                        .color(Color32::DARK_GRAY),
+                );
+            });
+
+            let (snippet, matches) = snippet(&result.text, query, SNIPPET_CONTEXT);
+            let format = TextFormat {
+                font_id: FontId::proportional(13.),
+                color: ui.visuals().text_color(),
+                ..Default::default()
+            };
+            ui.label(highlighted(&snippet, &matches, format));
+        })
+        .response
+        .interact(Sense::click());
+
+    if response.hovered() {
+        ui.painter()
+            .rect_filled(response.rect, 4., Color32::from_black_alpha(10));
+    }
+    response.on_hover_cursor(CursorIcon::PointingHand)
+}
+
+/// Lays out `text` with a background behind the given byte ranges.
+pub fn highlighted(text: &str, matches: &[Range<usize>], format: TextFormat) -> LayoutJob {
+    let highlight = TextFormat {
+        background: HIGHLIGHT_COLOR,
+        ..format.clone()
+    };
+
+    let mut job = LayoutJob::default();
+    let mut end = 0;
+    for range in matches {
+        job.append(&text[end..range.start], 0., format.clone());
+        job.append(&text[range.clone()], 0., highlight.clone());
+        end = range.end;
+    }
+    job.append(&text[end..], 0., format);
+    job
+}
+
+/// Finds the non-overlapping occurrences of `query` in `text`, ignoring case.
+pub fn find_matches(text: &str, query: &str) -> Vec<Range<usize>> {
+    let mut matches = Vec::new();
+    if query.is_empty() {
+        return matches;
+    }
+
+    let mut from = 0;
+    for (start, _) in text.char_indices() {
+        if start < from {
+            continue;
+        }
+        let mut end = start;
+        let mut chars = text[start..].chars();
+        let found = query.chars().all(|q| match chars.next() {
+            Some(c) if c.to_lowercase().eq(q.to_lowercase()) => {
+                end += c.len_utf8();
+                true
+            }
+            _ => false,
+        });
+        if found {
+            matches.push(start..end);
+            from = end;
+        }
+    }
+    matches
+}
+
+/// Cuts `text` to a single line around the first match of `query`, with the matches in it.
+fn snippet(text: &str, query: &str, context: usize) -> (String, Vec<Range<usize>>) {
+    let text = text.replace('\n', " ");
+    let first = match find_matches(&text, query).first() {
+        Some(first) => first.clone(),
+        None => return (text.chars().take(2 * context).collect(), Vec::new()),
+    };
+
+    let start = text[..first.start]
+        .char_indices()
+        .rev()
+        .take(context)
+        .last()
+        .map_or(first.start, |(i, _)| i);
+    let end = text[first.end..]
+        .char_indices()
+        .nth(context)
+        .map_or(text.len(), |(i, _)| first.end + i);
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push('…');
+    }
+    snippet.push_str(&text[start..end]);
+    if end < text.len() {
+        snippet.push('…');
+    }
+    let matches = find_matches(&snippet, query);
+    (snippet, matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_matches() {
+        let text = "Größe, größer, GRÖSSTE";
+        let matches: Vec<_> = find_matches(text, "grö")
+            .into_iter()
+            .map(|range| &text[range])
+            .collect();
+        assert_eq!(matches, vec!["Grö", "grö", "GRÖ"]);
+
+        assert_eq!(find_matches("aaaa", "aa"), vec![0..2, 2..4]);
+        assert!(find_matches("abc", "").is_empty());
+        assert!(find_matches("abc", "abcd").is_empty());
+    }
+
+    #[test]
+    fn test_snippet() {
+        let (text, matches) = snippet("a long text\nwith a needle in it", "NEEDLE", 5);
+        assert_eq!(text, "…th a needle in i…");
+        assert_eq!(matches.len(), 1);
+        assert_eq!(&text[matches[0].clone()], "needle");
+
+        let (text, matches) = snippet("needle", "needle", 5);
+        assert_eq!(text, "needle");
+        assert_eq!(matches, vec![0..6]);
+    }
+}
@@ -4,7 +4,7 @@ use epaint::Color32;
 use super::{accounts, chat_list};
 use crate::state::AppState;
 
-pub fn render_sidebar(ctx: &Context, state: &AppState) {
+pub fn render_sidebar(ctx: &Context, state: &mut AppState) {
     SidePanel::left("sidebar")
         .frame(Frame::default().fill(Color32::from_gray(250)))
         .default_width(330.)